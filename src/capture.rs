@@ -0,0 +1,498 @@
+//! Contains all the logic for capturing stderr and stdout.
+//!
+//! We don't use `nix` for this stuff because we will also attempt it on
+//! Windows using nearly-identical code, and it wouldn't make sense to have
+//! almost-totally-parallel nix-based safe code and non-nix-based unsafe code.
+
+use std::{io::IsTerminal, thread::JoinHandle};
+
+#[cfg(feature = "capture-stdout")]
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use libc::c_int;
+use parking_lot::Mutex;
+
+#[cfg(feature = "capture-stdout")]
+use crate::{liso, Line};
+use crate::{Output, Request};
+
+#[cfg(feature = "capture-stderr")]
+const STDERR_FD: c_int = 2;
+#[cfg(feature = "capture-stdout")]
+const STDOUT_FD: c_int = 1;
+
+/// A standard file descriptor that has been replaced with a pipe we read
+/// from.
+struct Capture {
+    /// The thread reading from the pipe. When it's done, it puts the real
+    /// file descriptor back.
+    thread: JoinHandle<()>,
+    /// A copy of the real file descriptor, from before the capture.
+    real_fd: c_int,
+    /// A copy of the write end of the pipe, so that a paused capture can be
+    /// resumed.
+    pipe_w: c_int,
+}
+
+/// One of the file descriptors we know how to capture.
+struct Target {
+    fd: c_int,
+    name: &'static str,
+    capture: &'static Mutex<Option<Capture>>,
+    is_terminal: fn() -> bool,
+    /// Turns a captured line into a request.
+    make_request: fn(String) -> Request,
+    /// Turns one of our own error messages into a request.
+    make_error: fn(String) -> Request,
+}
+
+#[cfg(feature = "capture-stderr")]
+static STDERR_CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+#[cfg(feature = "capture-stderr")]
+const STDERR: Target = Target {
+    fd: STDERR_FD,
+    name: "stderr",
+    capture: &STDERR_CAPTURE,
+    is_terminal: || std::io::stderr().is_terminal(),
+    make_request: Request::StderrLine,
+    make_error: Request::StderrLine,
+};
+
+#[cfg(feature = "capture-stdout")]
+static STDOUT_CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+#[cfg(feature = "capture-stdout")]
+const STDOUT: Target = Target {
+    fd: STDOUT_FD,
+    name: "stdout",
+    capture: &STDOUT_CAPTURE,
+    is_terminal: || std::io::stdout().is_terminal(),
+    make_request: |mut line| {
+        if line.ends_with('\r') {
+            line.pop();
+        }
+        Request::Output(Line::from_string(line))
+    },
+    make_error: |error| Request::Output(liso!(theme = "error", error)),
+};
+
+/// The file descriptor that the terminal is really on, while stdout is
+/// being captured. -1 if it isn't.
+#[cfg(feature = "capture-stdout")]
+static REAL_STDOUT_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// All the targets that are enabled.
+const TARGETS: &[Target] = &[
+    #[cfg(feature = "capture-stderr")]
+    STDERR,
+    #[cfg(feature = "capture-stdout")]
+    STDOUT,
+];
+
+fn pipe() -> Result<(c_int, c_int), c_int> {
+    #[cfg(any(target_family = "windows", target_family = "unix"))]
+    loop {
+        let mut fds = [0; 2];
+        #[cfg(target_family = "windows")]
+        let result =
+            unsafe { libc::pipe(fds.as_mut_ptr(), 128, libc::O_TEXT) };
+        #[cfg(target_family = "unix")]
+        let result = unsafe { libc::pipe(fds.as_mut_ptr()) };
+        if result == 0 {
+            return Ok((fds[0], fds[1]));
+        } else {
+            let errno = errno::errno().0;
+            if errno == libc::EINTR {
+                continue;
+            } else {
+                return Err(errno);
+            }
+        }
+    }
+    #[allow(unreachable_code)]
+    return Err(libc::ENOSYS);
+}
+
+fn dup2(src: c_int, dst: c_int) -> Result<(), c_int> {
+    #[cfg(any(target_family = "windows", target_family = "unix"))]
+    loop {
+        errno::set_errno(errno::Errno(0));
+        let result = unsafe { libc::dup2(src, dst) };
+        if result >= 0 {
+            if result != dst {
+                let errno = errno::errno().0;
+                let errno = if errno == 0 { libc::ENOSYS } else { errno };
+                unsafe {
+                    libc::close(result);
+                }
+                return Err(errno);
+            }
+            return Ok(());
+        } else {
+            let errno = errno::errno().0;
+            if errno == libc::EINTR {
+                continue;
+            } else {
+                return Err(errno);
+            }
+        }
+    }
+    #[allow(unreachable_code)]
+    return Err(libc::ENOSYS);
+}
+
+fn dup(src: c_int) -> Result<c_int, c_int> {
+    #[cfg(any(target_family = "windows", target_family = "unix"))]
+    loop {
+        errno::set_errno(errno::Errno(0));
+        let result = unsafe { libc::dup(src) };
+        if result >= 0 {
+            return Ok(result);
+        } else {
+            let errno = errno::errno().0;
+            if errno == libc::EINTR {
+                continue;
+            } else {
+                return Err(errno);
+            }
+        }
+    }
+    #[allow(unreachable_code)]
+    return Err(libc::ENOSYS);
+}
+
+fn close(_fd: c_int) {
+    #[cfg(any(target_family = "windows", target_family = "unix"))]
+    unsafe {
+        libc::close(_fd);
+    }
+}
+
+fn read(fd: c_int, buf: &mut [u8]) -> Result<usize, c_int> {
+    #[cfg(any(target_family = "windows", target_family = "unix"))]
+    loop {
+        let result = unsafe {
+            libc::read(
+                fd,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len() as libc::size_t,
+            )
+        };
+        if result >= 0 {
+            return Ok(result as usize);
+        } else {
+            let errno = errno::errno().0;
+            if errno == libc::EINTR {
+                continue;
+            } else {
+                return Err(errno);
+            }
+        }
+    }
+    #[allow(unreachable_code)]
+    return Err(libc::ENOSYS);
+}
+
+fn write_all(fd: c_int, mut buf: &[u8]) -> Result<(), c_int> {
+    #[cfg(any(target_family = "windows", target_family = "unix"))]
+    while !buf.is_empty() {
+        let result = unsafe {
+            libc::write(
+                fd,
+                buf.as_ptr() as *const libc::c_void,
+                buf.len() as libc::size_t,
+            )
+        };
+        if result >= 0 {
+            buf = &buf[result as usize..];
+        } else {
+            let errno = errno::errno().0;
+            if errno == libc::EINTR {
+                continue;
+            } else {
+                return Err(errno);
+            }
+        }
+    }
+    if buf.is_empty() {
+        Ok(())
+    } else {
+        Err(libc::ENOSYS)
+    }
+}
+
+/// Returns the file descriptor to use to write to the terminal: stdout,
+/// unless stdout is being captured.
+#[cfg(feature = "capture-stdout")]
+pub(crate) fn real_stdout_fd() -> c_int {
+    match REAL_STDOUT_FD.load(Ordering::Acquire) {
+        -1 => STDOUT_FD,
+        x => x,
+    }
+}
+
+/// Writes to the terminal, even while stdout is being captured. Everything
+/// written is held until the next flush.
+#[cfg(feature = "capture-stdout")]
+pub(crate) struct RealStdout {
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "capture-stdout")]
+impl RealStdout {
+    pub fn new() -> RealStdout {
+        RealStdout { buf: Vec::new() }
+    }
+}
+
+#[cfg(feature = "capture-stdout")]
+impl std::io::Write for RealStdout {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        let result = write_all(real_stdout_fd(), &self.buf);
+        self.buf.clear();
+        result.map_err(std::io::Error::from_raw_os_error)
+    }
+}
+
+/// Captures every file descriptor that the enabled features ask us to.
+pub(crate) fn attempt_captures(output: &Output) {
+    for target in TARGETS.iter() {
+        attempt_capture(
+            target,
+            Output {
+                tx: output.tx.clone(),
+            },
+        );
+    }
+}
+
+fn attempt_capture(target: &'static Target, output: Output) {
+    let send_error = {
+        let tx = output.tx.clone();
+        move |error: String| {
+            let _ = tx.send((target.make_error)(error));
+        }
+    };
+    let send = move |line: String| {
+        output.tx.send((target.make_request)(line)).is_ok()
+    };
+    // wait until previous capture is over, just in case `InputOutput`s are
+    // created and destroyed quickly
+    let mut lock;
+    loop {
+        wait_until_not_captured(target);
+        lock = target.capture.lock();
+        match lock.as_ref() {
+            None => break,
+            Some(_) => continue,
+        }
+    }
+    if !(target.is_terminal)() {
+        return;
+    }
+    let (r, w) = match pipe() {
+        Ok(x) => x,
+        Err(x) => {
+            send_error(format!(
+                "pipe() returned error {:?} when attempting to capture {}.",
+                x, target.name
+            ));
+            return;
+        }
+    };
+    let real_fd = match dup(target.fd) {
+        Ok(x) => x,
+        Err(x) => {
+            close(r);
+            close(w);
+            send_error(format!(
+                "dup() returned error {:?} when attempting to capture {}.",
+                x, target.name
+            ));
+            return;
+        }
+    };
+    if let Err(x) = dup2(w, target.fd) {
+        close(r);
+        close(w);
+        close(real_fd);
+        send_error(format!(
+            "dup2() returned error {:?} when attempting to capture {}.",
+            x, target.name
+        ));
+        return;
+    }
+    // (`w` stays open, so that we can resume after a pause)
+    #[cfg(feature = "capture-stdout")]
+    if target.fd == STDOUT_FD {
+        REAL_STDOUT_FD.store(real_fd, Ordering::Release);
+    }
+    let thread = std::thread::spawn(move || {
+        let mut buf = vec![0u8; 128];
+        let mut buf_pos = 0;
+        'outer: loop {
+            if buf_pos == buf.len() {
+                buf.resize(buf.len() + 128, 0u8);
+            }
+            match read(r, &mut buf[buf_pos..]) {
+                Ok(0) => {
+                    // the captured file was closed?!
+                    if buf_pos > 0
+                        && !send(
+                            String::from_utf8_lossy(&buf[..buf_pos])
+                                .to_string(),
+                        )
+                    {
+                        let _ = write_all(real_fd, &buf[..buf_pos]);
+                    }
+                    buf_pos = 0;
+                    break;
+                }
+                Ok(x) => {
+                    let mut last_newline_pos = None;
+                    let end_pos = buf_pos + x;
+                    while let Some(p) = buf[buf_pos..end_pos]
+                        .iter()
+                        .position(|x| *x == b'\n')
+                        .map(|x| x + buf_pos)
+                    {
+                        let start_pos =
+                            last_newline_pos.map(|x| x + 1).unwrap_or(0);
+                        if !send(
+                            String::from_utf8_lossy(&buf[start_pos..p])
+                                .to_string(),
+                        ) {
+                            // can't do anything sensible with an error here
+                            let _ =
+                                write_all(real_fd, &buf[start_pos..end_pos]);
+                            buf_pos = 0;
+                            break 'outer;
+                        }
+                        last_newline_pos = Some(p);
+                        buf_pos = p + 1;
+                    }
+                    buf_pos = end_pos;
+                    if let Some(p) = last_newline_pos {
+                        buf.copy_within(p + 1..end_pos, 0);
+                        buf_pos -= p + 1;
+                    }
+                }
+                Err(x) => {
+                    if buf_pos > 0
+                        && !send(
+                            String::from_utf8_lossy(&buf[..buf_pos])
+                                .to_string(),
+                        )
+                    {
+                        let _ = write_all(real_fd, &buf[..buf_pos]);
+                    }
+                    send_error(format!(
+                        "read() returned error {:?} when reading from {}.",
+                        x, target.name
+                    ));
+                    buf_pos = 0;
+                    break;
+                }
+            }
+        }
+        assert_eq!(
+            buf_pos, 0,
+            "INTERNAL LISO ERROR: buf contents not fully handled when liso closed down!"
+        );
+        // Small possibility that some bytes will be mixed up if a lot of
+        // output is happening at once. Oh well. That's an unavoidable cost of
+        // your program bypassing the "so" part of "liso".
+        //
+        // There's also a small possibility that one or more lines we sent
+        // "successfully" were lost. Oh well.
+        dup2(real_fd, target.fd)
+            .expect("Unable to reduplicate captured file back into place!");
+        #[cfg(feature = "capture-stdout")]
+        if target.fd == STDOUT_FD {
+            REAL_STDOUT_FD.store(-1, Ordering::Release);
+        }
+        close(real_fd);
+        // Any remaining output waiting in the pipe, process.
+        while let Ok(amount) = read(r, &mut buf[..]) {
+            if amount == 0 {
+                break;
+            }
+            let _ = write_all(target.fd, &buf[..amount]);
+        }
+        close(r);
+    });
+    *lock = Some(Capture {
+        thread,
+        real_fd,
+        pipe_w: w,
+    });
+}
+
+/// Temporarily puts every captured file descriptor back the way it was, so
+/// that whoever gets the terminal while we're suspended can use it.
+pub(crate) fn pause_captures() {
+    for target in TARGETS.iter() {
+        if let Some(capture) = target.capture.lock().as_ref() {
+            let _ = dup2(capture.real_fd, target.fd);
+        }
+    }
+}
+
+/// Undoes `pause_captures`.
+pub(crate) fn resume_captures() {
+    for target in TARGETS.iter() {
+        if let Some(capture) = target.capture.lock().as_ref() {
+            let _ = dup2(capture.pipe_w, target.fd);
+        }
+    }
+}
+
+/// Ends every capture, putting the real file descriptors back.
+pub(crate) fn wait_until_not_captured_all() {
+    #[cfg(feature = "capture-stdout")]
+    {
+        use std::io::Write;
+        // (anything still in Rust's buffer should go through us)
+        let _ = std::io::stdout().flush();
+    }
+    for target in TARGETS.iter() {
+        wait_until_not_captured(target);
+    }
+}
+
+fn wait_until_not_captured(target: &Target) {
+    let mut lock = target.capture.lock();
+    if let Some(capture) = lock.take() {
+        // close both write ends of the pipe, so the thread sees the end
+        close(target.fd); // :(
+        close(capture.pipe_w);
+        let _ = capture.thread.join();
+    }
+    // Do not drop the lock until here! Nobody else should be allowed to
+    // think they can join before us!
+    drop(lock);
+}
+
+#[cfg(all(test, feature = "capture-stdout"))]
+mod test {
+    use super::*;
+    #[test]
+    fn stdout_requests() {
+        let line = |request| match request {
+            Request::Output(line) => line,
+            _ => panic!("unexpected request"),
+        };
+        assert_eq!(line((STDOUT.make_request)("a\tb".into())), liso!("a\tb"));
+        assert_eq!(
+            line((STDOUT.make_request)("crlf\r".into())),
+            liso!("crlf")
+        );
+        assert_eq!(
+            line((STDOUT.make_error)("oops".into())),
+            liso!(theme = "error", "oops")
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Recording exactly what a session looked like, as an asciinema cast.
+
+use super::*;
+
+use std::{
+    fs::File,
+    io,
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A recording of everything Liso draws on the terminal, written as an
+/// [asciinema v2][1] `.cast` file. Install one with
+/// [`Output::set_cast_recording`][2].
+///
+/// Where a [`Transcript`](struct.Transcript.html) records what was said, a
+/// cast records what was *seen*: every byte Liso sends to the terminal,
+/// timestamped, including prompt redraws, status changes, and spinners. Play
+/// it back with `asciinema play`, or attach it to a bug report.
+///
+/// The header is written as soon as the recording is installed, using the
+/// terminal size at that moment. If the terminal is resized later, a resize
+/// event is recorded. Keystrokes are only recorded if you ask for them with
+/// [`record_input`](#method.record_input), since they may include things
+/// like passwords.
+///
+/// Only ANSI terminals can be recorded. Output from programs run with
+/// [`suspend_and_run`][3] isn't recorded, since Liso doesn't see it. If
+/// writing to the cast ever fails, Liso prints an error and stops recording.
+///
+/// ```rust,no_run
+/// # use liso::CastRecording;
+/// # let io = liso::InputOutput::new();
+/// io.set_cast_recording(Some(CastRecording::create("session.cast")
+///                            .unwrap()
+///                            .title("Reproducing bug #42")
+///                            .record_input(true)));
+/// ```
+///
+/// [1]: https://docs.asciinema.org/manual/asciicast/v2/
+/// [2]: struct.Output.html#method.set_cast_recording
+/// [3]: struct.Output.html#method.suspend_and_run
+pub struct CastRecording {
+    writer: Box<dyn Write + Send>,
+    title: Option<String>,
+    record_input: bool,
+    /// When the header was written. Event times are relative to this.
+    started: Option<Instant>,
+    size: (u32, u32),
+    /// The start of a UTF-8 sequence that was cut off at the end of the last
+    /// chunk of output.
+    output_tail: Vec<u8>,
+    /// The same, for input.
+    input_tail: Vec<u8>,
+}
+
+impl CastRecording {
+    /// Creates a recording that writes to the given writer.
+    pub fn new<W>(writer: W) -> CastRecording
+    where
+        W: Write + Send + 'static,
+    {
+        CastRecording {
+            writer: Box::new(writer),
+            title: None,
+            record_input: false,
+            started: None,
+            size: (0, 0),
+            output_tail: Vec::new(),
+            input_tail: Vec::new(),
+        }
+    }
+    /// Creates a recording that writes to the given file. If the file already
+    /// exists, it is replaced.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<CastRecording> {
+        Ok(CastRecording::new(File::create(path)?))
+    }
+    /// Sets the title stored in the header of the cast.
+    pub fn title<T>(mut self, title: T) -> CastRecording
+    where
+        T: Into<String>,
+    {
+        self.title = Some(title.into());
+        self
+    }
+    /// Sets whether keystrokes are recorded, as input events. The default is
+    /// not to record them.
+    pub fn record_input(mut self, record_input: bool) -> CastRecording {
+        self.record_input = record_input;
+        self
+    }
+    /// Writes the header. Called when the recording is installed.
+    fn start(&mut self, size: (u32, u32)) -> io::Result<()> {
+        self.start_at(SystemTime::now(), size)
+    }
+    fn start_at(
+        &mut self,
+        time: SystemTime,
+        size: (u32, u32),
+    ) -> io::Result<()> {
+        let timestamp = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        write!(
+            self.writer,
+            "{{\"version\":2,\"width\":{},\"height\":{},\"timestamp\":{}",
+            size.0, size.1, timestamp
+        )?;
+        if let Some(title) = self.title.as_ref() {
+            write!(self.writer, ",\"title\":\"{}\"", json_escape(title))?;
+        }
+        if let Ok(term) = std::env::var("TERM") {
+            write!(
+                self.writer,
+                ",\"env\":{{\"TERM\":\"{}\"}}",
+                json_escape(&term)
+            )?;
+        }
+        writeln!(self.writer, "}}")?;
+        self.writer.flush()?;
+        self.started = Some(Instant::now());
+        self.size = size;
+        Ok(())
+    }
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let elapsed = self
+            .started
+            .map(|x| x.elapsed())
+            .unwrap_or_default()
+            .as_secs_f64();
+        writeln!(
+            self.writer,
+            "[{:.6}, \"{}\", \"{}\"]",
+            elapsed,
+            code,
+            json_escape(data)
+        )?;
+        self.writer.flush()
+    }
+    /// Records some bytes that were sent to the terminal, which is currently
+    /// the given size.
+    fn output(&mut self, bytes: &[u8], size: (u32, u32)) -> io::Result<()> {
+        if size != self.size {
+            self.size = size;
+            self.event("r", &format!("{}x{}", size.0, size.1))?;
+        }
+        let text = take_utf8(&mut self.output_tail, bytes);
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.event("o", &text)
+    }
+    /// Records some bytes that were received from the terminal, if we're
+    /// recording input.
+    fn input(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if !self.record_input {
+            return Ok(());
+        }
+        let text = take_utf8(&mut self.input_tail, bytes);
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.event("i", &text)
+    }
+}
+
+/// Appends `bytes` to `tail`, and takes as much of the result as is complete
+/// UTF-8, leaving any incomplete sequence at the end behind in `tail`.
+/// Invalid sequences are replaced with U+FFFD.
+fn take_utf8(tail: &mut Vec<u8>, bytes: &[u8]) -> String {
+    tail.extend_from_slice(bytes);
+    let complete = match std::str::from_utf8(tail) {
+        Ok(_) => tail.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // (an invalid sequence, rather than an incomplete one)
+        Err(_) => tail.len(),
+    };
+    let ret = String::from_utf8_lossy(&tail[..complete]).into_owned();
+    tail.drain(..complete);
+    ret
+}
+
+/// The cast recording (if any) of a terminal, shared between the worker and
+/// the terminal's input thread.
+#[derive(Default)]
+pub(crate) struct CastSlot {
+    recording: Option<CastRecording>,
+    /// Why the last recording stopped, if it stopped because of an error that
+    /// hasn't been reported yet.
+    error: Option<io::Error>,
+}
+
+pub(crate) type SharedCast = Arc<Mutex<CastSlot>>;
+
+impl CastSlot {
+    /// Replaces the current recording (if any) with the given one, writing
+    /// its header.
+    pub fn set(&mut self, recording: Option<CastRecording>, size: (u32, u32)) {
+        self.recording = recording;
+        self.attempt(|recording| recording.start(size));
+    }
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+    pub fn output(&mut self, bytes: &[u8], size: (u32, u32)) {
+        self.attempt(|recording| recording.output(bytes, size));
+    }
+    pub fn input(&mut self, bytes: &[u8]) {
+        self.attempt(|recording| recording.input(bytes));
+    }
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+    fn attempt<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut CastRecording) -> io::Result<()>,
+    {
+        if let Some(recording) = self.recording.as_mut() {
+            if let Err(e) = f(recording) {
+                self.recording = None;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    /// A writer whose output we can look at afterward.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    #[test]
+    fn cast_events() {
+        let buf = SharedBuf::default();
+        let mut recording =
+            CastRecording::new(buf.clone()).title("Test \"cast\"");
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        recording.start_at(time, (80, 24)).unwrap();
+        // "é" cut in half, between two chunks
+        recording.output(b"\x1B[1mcaf\xC3", (80, 24)).unwrap();
+        recording.output(b"\xA9\r\n", (100, 30)).unwrap();
+        recording.input(b"q").unwrap();
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with(
+            "{\"version\":2,\"width\":80,\"height\":24,\
+             \"timestamp\":1000000000,\"title\":\"Test \\\"cast\\\"\""
+        ));
+        assert_eq!(lines.len(), 4);
+        let events: Vec<&str> = lines[1..]
+            .iter()
+            .map(|x| x.split_once(", ").unwrap().1)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "\"o\", \"\\u001b[1mcaf\"]",
+                "\"r\", \"100x30\"]",
+                "\"o\", \"\u{E9}\\r\\n\"]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Figures out how many colors the terminal can display, and converts colors
+//! down to ones it can.
+
+use super::*;
+
+use std::path::{Path, PathBuf};
+
+/// How many distinct colors a terminal is able to display. Liso detects this
+/// automatically (see [`detect`](#method.detect)) and converts every
+/// [`Color`](enum.Color.html) it outputs to the nearest color the terminal can
+/// actually show.
+///
+/// Variants are ordered from least to most capable, so you can compare them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorDepth {
+    /// No color at all. Styles such as bold and inverse video may still work.
+    Monochrome,
+    /// The eight named colors.
+    Ansi8,
+    /// The eight named colors, and bright versions of them.
+    /// ([`Color::Indexed`](enum.Color.html#variant.Indexed) 0 through 15.)
+    Ansi16,
+    /// The xterm 256-color palette.
+    Indexed256,
+    /// Arbitrary 24-bit color.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Work out how many colors the terminal supports, based on the
+    /// environment. In order of precedence:
+    ///
+    /// - If `NO_COLOR` is set to anything other than the empty string, we
+    ///   don't use color at all.
+    /// - If `COLORTERM` is `truecolor` or `24bit`, we use 24-bit color.
+    /// - If `TERM` ends in `-direct` we use 24-bit color, and if it contains
+    ///   `256color` we use the 256-color palette.
+    /// - If terminfo has an entry for `TERM`, we believe its `colors`
+    ///   capability.
+    /// - Otherwise, we assume only the eight named colors are available.
+    pub fn detect() -> ColorDepth {
+        let no_color =
+            std::env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty());
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        let terminfo_colors = term.as_deref().and_then(terminfo_max_colors);
+        ColorDepth::from_environment(
+            no_color,
+            colorterm.as_deref(),
+            term.as_deref(),
+            terminfo_colors,
+        )
+    }
+    // The logic of `detect`, separated out from actually reading the
+    // environment.
+    fn from_environment(
+        no_color: bool,
+        colorterm: Option<&str>,
+        term: Option<&str>,
+        terminfo_colors: Option<i32>,
+    ) -> ColorDepth {
+        if no_color {
+            return ColorDepth::Monochrome;
+        }
+        if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+            return ColorDepth::TrueColor;
+        }
+        if let Some(term) = term {
+            if term.ends_with("-direct") {
+                return ColorDepth::TrueColor;
+            } else if term.contains("256color") {
+                return ColorDepth::Indexed256;
+            }
+        }
+        match terminfo_colors {
+            Some(x) if x >= 1 << 24 => ColorDepth::TrueColor,
+            Some(x) if x >= 256 => ColorDepth::Indexed256,
+            Some(x) if x >= 16 => ColorDepth::Ansi16,
+            Some(x) if x >= 8 => ColorDepth::Ansi8,
+            Some(_) => ColorDepth::Monochrome,
+            None => ColorDepth::Ansi8,
+        }
+    }
+}
+
+impl Color {
+    /// Returns the nearest color that can be displayed with the given
+    /// [`ColorDepth`](enum.ColorDepth.html), or `None` if the answer is "no
+    /// color at all". Colors that can already be displayed are returned
+    /// unchanged.
+    ///
+    /// ```rust
+    /// # use liso::{Color, ColorDepth};
+    /// assert_eq!(Color::Rgb(255, 135, 0).downgrade(ColorDepth::Indexed256),
+    ///            Some(Color::Indexed(208)));
+    /// assert_eq!(Color::Indexed(208).downgrade(ColorDepth::Ansi16),
+    ///            Some(Color::Yellow));
+    /// assert_eq!(Color::Indexed(11).downgrade(ColorDepth::Ansi8),
+    ///            Some(Color::Yellow));
+    /// assert_eq!(Color::Yellow.downgrade(ColorDepth::Monochrome), None);
+    /// ```
+    pub fn downgrade(self, depth: ColorDepth) -> Option<Color> {
+        match (depth, self) {
+            (ColorDepth::Monochrome, _) => None,
+            (ColorDepth::TrueColor, _) => Some(self),
+            (_, Color::Indexed(x)) if x < 8 => Some(ANSI_COLORS[x as usize]),
+            (ColorDepth::Indexed256, Color::Rgb(r, g, b)) => {
+                // The first sixteen entries are often themed, so we can't
+                // count on them looking like anything in particular.
+                Some(Color::Indexed(nearest_in_cube_or_ramp((r, g, b))))
+            }
+            (ColorDepth::Indexed256, _) => Some(self),
+            (ColorDepth::Ansi16, Color::Indexed(x)) if x < 16 => Some(self),
+            (ColorDepth::Ansi16, Color::Indexed(_) | Color::Rgb(..)) => {
+                match nearest_in_palette(self.as_rgb(), &XTERM_PALETTE) {
+                    x if x < 8 => Some(ANSI_COLORS[x]),
+                    x => Some(Color::Indexed(x as u8)),
+                }
+            }
+            (ColorDepth::Ansi16, _) => Some(self),
+            (ColorDepth::Ansi8, _) => Some(self.as_named()),
+        }
+    }
+}
+
+/// Finds the entry in the 256-color palette's color cube (16 through 231) or
+/// grayscale ramp (232 through 255) that is closest to the given color. Gives
+/// the same answer as `nearest_in_palette` would, without building a palette
+/// to search.
+fn nearest_in_cube_or_ramp(rgb: (u8, u8, u8)) -> u8 {
+    let distance = |other: (u8, u8, u8)| {
+        let dr = rgb.0 as i32 - other.0 as i32;
+        let dg = rgb.1 as i32 - other.1 as i32;
+        let db = rgb.2 as i32 - other.2 as i32;
+        dr * dr + dg * dg + db * db
+    };
+    // Each channel of the cube can be considered separately. The levels are
+    // 0, 95, 135, 175, 215, and 255; ties go to the darker level.
+    let cube_step = |x: u8| match x {
+        0..=47 => 0,
+        48..=114 => 1,
+        x => (x as u32 - 115).div_ceil(40) + 1,
+    };
+    let cube_index =
+        16 + 36 * cube_step(rgb.0) + 6 * cube_step(rgb.1) + cube_step(rgb.2);
+    // The nearest gray is the one nearest the average of the channels. The
+    // levels are 8, 18, ..., 238; again, ties go to the darker level.
+    let sum = rgb.0 as i32 + rgb.1 as i32 + rgb.2 as i32;
+    let ramp_step = (sum - 24 + 14).div_euclid(30).clamp(0, 23);
+    let ramp_index = 232 + ramp_step as u32;
+    let cube = Color::Indexed(cube_index as u8).as_rgb();
+    let ramp = Color::Indexed(ramp_index as u8).as_rgb();
+    if distance(ramp) < distance(cube) {
+        ramp_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Parses a color from a string, as used in [markup][1]. Accepts:
+///
+/// - The name of one of the eight named colors, in any case (`red`, `Cyan`)
+/// - An xterm palette index from 0 to 255 (`208`)
+/// - A 24-bit color in hexadecimal, as in CSS (`#ff8700` or `#f80`)
+///
+/// ```rust
+/// # use liso::Color;
+/// assert_eq!("Magenta".parse(), Ok(Color::Magenta));
+/// assert_eq!("208".parse(), Ok(Color::Indexed(208)));
+/// assert_eq!("#ff8700".parse(), Ok(Color::Rgb(255, 135, 0)));
+/// assert!("chartreuse".parse::<Color>().is_err());
+/// ```
+///
+/// [1]: struct.Line.html#method.from_markup
+impl FromStr for Color {
+    type Err = ParseColorError;
+    fn from_str(s: &str) -> Result<Color, ParseColorError> {
+        if let Some(hex) = s.strip_prefix('#') {
+            let digits: Option<Vec<u8>> = hex
+                .chars()
+                .map(|x| x.to_digit(16).map(|x| x as u8))
+                .collect();
+            return match digits.as_deref() {
+                Some(&[r, g, b]) => Ok(Color::Rgb(r * 17, g * 17, b * 17)),
+                Some(&[r1, r2, g1, g2, b1, b2]) => {
+                    Ok(Color::Rgb(r1 << 4 | r2, g1 << 4 | g2, b1 << 4 | b2))
+                }
+                _ => Err(ParseColorError(())),
+            };
+        }
+        if let Ok(index) = s.parse::<u8>() {
+            return Ok(Color::Indexed(index));
+        }
+        ANSI_COLORS
+            .iter()
+            .find(|x| format!("{x:?}").eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or(ParseColorError(()))
+    }
+}
+
+/// The error returned when a string can't be
+/// [parsed as a `Color`](enum.Color.html#impl-FromStr-for-Color).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError(());
+
+impl std::fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("not a color name, palette index, or #rrggbb color")
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+/// Directories that might contain a terminfo database, in the order ncurses
+/// searches them.
+fn terminfo_dirs() -> Vec<PathBuf> {
+    let mut ret = vec![];
+    if let Some(dir) = std::env::var_os("TERMINFO") {
+        ret.push(PathBuf::from(dir));
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        ret.push(Path::new(&home).join(".terminfo"));
+    }
+    if let Ok(dirs) = std::env::var("TERMINFO_DIRS") {
+        ret.extend(
+            dirs.split(':').filter(|x| !x.is_empty()).map(PathBuf::from),
+        );
+    }
+    ret.extend(
+        [
+            "/etc/terminfo",
+            "/lib/terminfo",
+            "/usr/share/terminfo",
+            "/usr/lib/terminfo",
+            "/usr/share/lib/terminfo",
+        ]
+        .iter()
+        .map(PathBuf::from),
+    );
+    ret
+}
+
+/// Look up the `colors` capability for the given terminal type, by finding
+/// and parsing its compiled terminfo entry.
+fn terminfo_max_colors(term: &str) -> Option<i32> {
+    let first = term.chars().next()?;
+    if term.contains('/') || term.starts_with('.') {
+        return None;
+    }
+    for dir in terminfo_dirs() {
+        // Most systems sort entries into directories by first letter, but
+        // some (e.g. macOS) use the hexadecimal value of that letter instead.
+        for subdir in [first.to_string(), format!("{:02x}", first as u32)] {
+            if let Ok(entry) = std::fs::read(dir.join(subdir).join(term)) {
+                return parse_terminfo_colors(&entry);
+            }
+        }
+    }
+    None
+}
+
+/// Extract the `colors` capability from a compiled terminfo entry. See
+/// `term(5)` for the format.
+fn parse_terminfo_colors(entry: &[u8]) -> Option<i32> {
+    // The index of `colors` among the numeric capabilities.
+    const COLORS: usize = 13;
+    let header = |i: usize| -> Option<usize> {
+        let bytes = entry.get(i * 2..i * 2 + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    let number_size = match header(0)? {
+        0o432 => 2,
+        0o1036 => 4,
+        _ => return None,
+    };
+    let names_size = header(1)?;
+    let bool_count = header(2)?;
+    let num_count = header(3)?;
+    if num_count <= COLORS {
+        return None;
+    }
+    let mut numbers_start = 12 + names_size + bool_count;
+    // numbers are aligned to an even byte boundary
+    numbers_start += numbers_start % 2;
+    let start = numbers_start + COLORS * number_size;
+    let bytes = entry.get(start..start + number_size)?;
+    let colors = if number_size == 2 {
+        i16::from_le_bytes([bytes[0], bytes[1]]) as i32
+    } else {
+        i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    // negative means "absent"
+    if colors < 0 {
+        None
+    } else {
+        Some(colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn depth_detection() {
+        use ColorDepth::*;
+        let detect = ColorDepth::from_environment;
+        assert_eq!(detect(true, Some("truecolor"), None, None), Monochrome);
+        assert_eq!(detect(false, Some("24bit"), None, Some(8)), TrueColor);
+        assert_eq!(detect(false, None, Some("xterm-direct"), None), TrueColor);
+        assert_eq!(
+            detect(false, None, Some("xterm-256color"), Some(8)),
+            Indexed256
+        );
+        assert_eq!(detect(false, None, Some("xterm"), Some(8)), Ansi8);
+        assert_eq!(detect(false, None, Some("rxvt"), Some(88)), Ansi16);
+        assert_eq!(detect(false, None, Some("vt100"), None), Ansi8);
+        assert_eq!(detect(false, None, Some("vt100"), Some(0)), Monochrome);
+    }
+    #[test]
+    fn downgrading() {
+        assert_eq!(
+            Color::Rgb(0, 0, 0).downgrade(ColorDepth::Indexed256),
+            Some(Color::Indexed(16))
+        );
+        assert_eq!(
+            Color::Rgb(128, 128, 128).downgrade(ColorDepth::Indexed256),
+            Some(Color::Indexed(244))
+        );
+        assert_eq!(
+            Color::Indexed(196).downgrade(ColorDepth::Ansi16),
+            Some(Color::Indexed(9))
+        );
+        assert_eq!(
+            Color::Indexed(6).downgrade(ColorDepth::Ansi16),
+            Some(Color::Cyan)
+        );
+        assert_eq!(
+            Color::Rgb(0, 0, 100).downgrade(ColorDepth::Ansi8),
+            Some(Color::Black)
+        );
+        assert_eq!(
+            Color::Magenta.downgrade(ColorDepth::Ansi8),
+            Some(Color::Magenta)
+        );
+        for depth in [
+            ColorDepth::Ansi8,
+            ColorDepth::Ansi16,
+            ColorDepth::Indexed256,
+        ] {
+            for x in 0..=255 {
+                let downgraded = Color::Indexed(x).downgrade(depth).unwrap();
+                assert!(downgraded.downgrade(depth) == Some(downgraded));
+            }
+        }
+    }
+    #[test]
+    fn cube_and_ramp_search() {
+        let cube_and_ramp: Vec<(u8, u8, u8)> =
+            (16..=255).map(|x| Color::Indexed(x).as_rgb()).collect();
+        // (steps of five hit every tie between two levels of the cube)
+        for r in (0..=255).step_by(5) {
+            for g in (0..=255).step_by(5) {
+                for b in (0..=255).step_by(5) {
+                    let brute = nearest_in_palette((r, g, b), &cube_and_ramp);
+                    assert_eq!(
+                        nearest_in_cube_or_ramp((r, g, b)),
+                        16 + brute as u8,
+                        "for {:?}",
+                        (r, g, b)
+                    );
+                }
+            }
+        }
+    }
+    #[test]
+    fn terminfo_parsing() {
+        // A minimal legacy-format entry with 14 numeric capabilities, the
+        // last of which (`colors`) is 256.
+        let mut entry = vec![];
+        for x in [0o432u16, 4, 1, 14, 0, 0] {
+            entry.extend_from_slice(&x.to_le_bytes());
+        }
+        entry.extend_from_slice(b"foo\0");
+        entry.push(1); // one boolean
+        entry.push(0); // alignment
+        for _ in 0..13 {
+            entry.extend_from_slice(&(-1i16).to_le_bytes());
+        }
+        entry.extend_from_slice(&256i16.to_le_bytes());
+        assert_eq!(parse_terminfo_colors(&entry), Some(256));
+        assert_eq!(parse_terminfo_colors(&entry[..entry.len() - 1]), None);
+    }
+}
@@ -0,0 +1,240 @@
+//! Running a child process and printing its output through Liso.
+
+use super::*;
+
+use std::{
+    io,
+    io::{BufRead, BufReader, Read},
+    process::{Command, ExitStatus, Stdio},
+    thread::JoinHandle,
+};
+
+/// Options for [`spawn_command_with`][1]: what to put in front of each line
+/// of a child process's output, and whether to tell you when it finishes.
+///
+/// ```rust,no_run
+/// # use liso::{liso, CommandOptions};
+/// # let io = liso::InputOutput::new();
+/// let options = CommandOptions::new()
+///     .stdout_prefix(liso!(dim, "build | "))
+///     .stderr_prefix(liso!(fg = red, "build | "))
+///     .report_exit(true);
+/// io.spawn_command_with(std::process::Command::new("make"), options)
+///     .unwrap();
+/// ```
+///
+/// [1]: struct.Output.html#method.spawn_command_with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandOptions {
+    stdout_prefix: Line,
+    stderr_prefix: Line,
+    report_exit: bool,
+}
+
+impl CommandOptions {
+    /// Options that print every line of output as-is, with no prefix, and
+    /// don't report when the process exits.
+    pub fn new() -> CommandOptions {
+        CommandOptions {
+            stdout_prefix: Line::new(),
+            stderr_prefix: Line::new(),
+            report_exit: false,
+        }
+    }
+    /// Text to put in front of every line the process writes to stdout.
+    pub fn stdout_prefix<T>(mut self, prefix: T) -> CommandOptions
+    where
+        T: Into<Line>,
+    {
+        self.stdout_prefix = prefix.into();
+        self
+    }
+    /// Text to put in front of every line the process writes to stderr.
+    pub fn stderr_prefix<T>(mut self, prefix: T) -> CommandOptions
+    where
+        T: Into<Line>,
+    {
+        self.stderr_prefix = prefix.into();
+        self
+    }
+    /// Whether to send a [`Response::Custom`][1] containing a
+    /// [`CommandFinished`](struct.CommandFinished.html) once the process has
+    /// exited and all of its output has been printed.
+    ///
+    /// [1]: enum.Response.html#variant.Custom
+    pub fn report_exit(mut self, report_exit: bool) -> CommandOptions {
+        self.report_exit = report_exit;
+        self
+    }
+}
+
+impl Default for CommandOptions {
+    fn default() -> CommandOptions {
+        CommandOptions::new()
+    }
+}
+
+/// Sent, inside a [`Response::Custom`][1], when a process started with
+/// [`report_exit`][2] turned on has finished.
+///
+/// ```rust,no_run
+/// # use liso::{CommandFinished, Response};
+/// # let mut io = liso::InputOutput::new();
+/// match io.read_blocking() {
+///     Response::Custom(x) if x.is::<CommandFinished>() => {
+///         let finished = x.downcast::<CommandFinished>().unwrap();
+///         io.println(format!("{} exited: {:?}", finished.id,
+///                            finished.status));
+///     }
+///     // ...
+///     _ => (),
+/// }
+/// ```
+///
+/// [1]: enum.Response.html#variant.Custom
+/// [2]: struct.CommandOptions.html#method.report_exit
+#[derive(Debug)]
+pub struct CommandFinished {
+    /// The process ID, the same as [`CommandHandle::id`][1].
+    ///
+    /// [1]: struct.CommandHandle.html#method.id
+    pub id: u32,
+    /// How the process exited, or why we couldn't find out.
+    pub status: io::Result<ExitStatus>,
+}
+
+/// A child process started by [`spawn_command`][1]. Dropping this doesn't
+/// affect the process, or the printing of its output.
+///
+/// [1]: struct.Output.html#method.spawn_command
+#[derive(Debug)]
+pub struct CommandHandle {
+    id: u32,
+    thread: JoinHandle<io::Result<ExitStatus>>,
+}
+
+impl CommandHandle {
+    /// Returns the process ID.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    /// Returns true if the process has exited and all of its output has been
+    /// printed.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+    /// Waits for the process to exit, and for all of its output to be
+    /// printed, and returns how it exited.
+    pub fn wait(self) -> io::Result<ExitStatus> {
+        self.thread.join().expect("Liso command thread panicked")
+    }
+}
+
+pub(crate) fn spawn_command(
+    output: OutputOnly,
+    mut command: Command,
+    options: CommandOptions,
+) -> io::Result<CommandHandle> {
+    // (Liso is using stdin, so the child can't)
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let id = child.id();
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|x| spawn_reader(x, output.clone(), options.stdout_prefix))
+            .transpose()?,
+        child
+            .stderr
+            .take()
+            .map(|x| spawn_reader(x, output.clone(), options.stderr_prefix))
+            .transpose()?,
+    ];
+    let report_exit = options.report_exit;
+    let thread = std::thread::Builder::new()
+        .name(format!("Liso command waiter for {}", id))
+        .spawn(move || {
+            for reader in readers.into_iter().flatten() {
+                let _ = reader.join();
+            }
+            let status = child.wait();
+            if report_exit {
+                let finished = CommandFinished {
+                    id,
+                    status: match status.as_ref() {
+                        Ok(x) => Ok(*x),
+                        Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+                    },
+                };
+                let _ = output.0.tx.send(Request::Custom(Box::new(finished)));
+            }
+            status
+        })?;
+    Ok(CommandHandle { id, thread })
+}
+
+/// Spawns a thread that prints every line from the given pipe.
+fn spawn_reader<R>(
+    pipe: R,
+    output: OutputOnly,
+    prefix: Line,
+) -> io::Result<JoinHandle<()>>
+where
+    R: Read + Send + 'static,
+{
+    std::thread::Builder::new()
+        .name("Liso command reader".to_owned())
+        .spawn(move || {
+            for bytes in BufReader::new(pipe).split(b'\n') {
+                let Ok(bytes) = bytes else { break };
+                let bytes = bytes.strip_suffix(b"\r").unwrap_or(&bytes);
+                let mut line = prefix.clone();
+                line.reset_all();
+                line.add_ansi_text(String::from_utf8_lossy(bytes));
+                // (keep reading even if Liso is gone, so that the child
+                // doesn't get stuck writing to a full pipe)
+                let _ = output.0.tx.send(Request::Output(line));
+            }
+        })
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    #[test]
+    fn spawn_command_output() {
+        let (output, rx) = Output::for_test();
+        let mut command = Command::new("sh");
+        command.args(["-c", "printf 'a\\r\\nb\\033[1mc\\n'; echo e >&2"]);
+        let options = CommandOptions::new()
+            .stderr_prefix(liso!(fg = red, "!"))
+            .report_exit(true);
+        let status = output
+            .spawn_command_with(command, options)
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert!(status.success());
+        let mut lines = vec![];
+        let mut finished = None;
+        for request in rx.try_iter() {
+            match request {
+                Request::Output(line) => lines.push(line),
+                Request::Custom(x) => {
+                    finished = Some(x.downcast::<CommandFinished>().unwrap())
+                }
+                _ => panic!("unexpected request"),
+            }
+        }
+        // (stdout and stderr lines can arrive in either order)
+        let stderr_line = liso!(fg = red, "!", reset, "e");
+        assert!(lines.contains(&stderr_line));
+        lines.retain(|x| *x != stderr_line);
+        assert_eq!(lines, [liso!("a"), liso!("b", bold, "c")]);
+        assert!(finished.unwrap().status.unwrap().success());
+    }
+}
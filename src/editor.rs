@@ -0,0 +1,102 @@
+//! Editing input in an external editor, like Readline's
+//! `edit-and-execute-command`.
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The editor to use if neither `VISUAL` nor `EDITOR` is set.
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+/// How many names to try for the temporary file before giving up.
+const MAX_TEMP_ATTEMPTS: u32 = 100;
+
+/// Writes the given text to a temporary file, opens it in the user's
+/// preferred editor, and returns what was in the file once the editor exits.
+/// The terminal must already have been handed over.
+pub(crate) fn edit_in_editor(text: &str) -> io::Result<String> {
+    let editor = std::env::var("VISUAL")
+        .ok()
+        .filter(|x| !x.trim().is_empty())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|x| !x.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_owned());
+    // (the editor can have arguments, e.g. `code --wait`)
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap();
+    let (file, path) = create_temp_file()?;
+    let result = run_editor(file, program, words, &path, text);
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Creates a new temporary file that only we can read, trying other names
+/// if the one we picked is already taken. (We create it ourselves, rather
+/// than opening an existing one, so that we only remove it if it's ours.)
+fn create_temp_file() -> io::Result<(File, PathBuf)> {
+    let mut attempts = 0;
+    loop {
+        let path = temp_path();
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                attempts += 1;
+                if attempts >= MAX_TEMP_ATTEMPTS {
+                    return Err(e);
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Fills in the temporary file, and runs the editor on it.
+fn run_editor<'a>(
+    mut file: File,
+    program: &str,
+    args: impl Iterator<Item = &'a str>,
+    path: &Path,
+    text: &str,
+) -> io::Result<String> {
+    file.write_all(text.as_bytes())?;
+    file.write_all(b"\n")?;
+    drop(file);
+    let status = Command::new(program).args(args).arg(path).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+    std::fs::read_to_string(path)
+}
+
+/// A path for a new temporary file, which (probably) doesn't exist yet.
+fn temp_path() -> PathBuf {
+    // (the counter makes sure that a retry gets a different name, even if
+    // the clock hasn't moved)
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.subsec_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir().join(format!(
+        "liso-input-{}-{:08x}-{}.txt",
+        std::process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
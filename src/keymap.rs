@@ -0,0 +1,329 @@
+//! Key bindings: which keys (and chords of keys) do what.
+
+use super::*;
+
+use std::{collections::HashMap, fmt};
+
+/// A key the user can press. More keys may be recognized in the future.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Key {
+    /// A key that produces a character. Control keys produce control
+    /// characters: control-A is `Key::Char('\u{1}')`, also written
+    /// `Key::ctrl('A')`. Tab, Return, and Escape are `'\t'`, `'\r'`, and
+    /// `'\u{1B}'`. Backspace is usually `'\u{7F}'`, but sometimes `'\u{8}'`.
+    Char(char),
+    /// The up arrow.
+    Up,
+    /// The down arrow.
+    Down,
+    /// The left arrow.
+    Left,
+    /// The right arrow.
+    Right,
+    /// The Home key.
+    Home,
+    /// The End key.
+    End,
+    /// The Page Up key.
+    PageUp,
+    /// The Page Down key.
+    PageDown,
+    /// The Delete key (sometimes labeled "Del"), which deletes forward. The
+    /// key that deletes backward is a `Char`; see above.
+    Delete,
+}
+
+impl Key {
+    /// The key for control plus the given letter (or one of `@[\]^_`).
+    /// Panics if `ch` isn't ASCII, since there's no such key.
+    ///
+    /// ```rust
+    /// # use liso::Key;
+    /// assert_eq!(Key::ctrl('X'), Key::Char('\u{18}'));
+    /// assert_eq!(Key::ctrl('x'), Key::Char('\u{18}'));
+    /// ```
+    pub fn ctrl(ch: char) -> Key {
+        assert!(ch.is_ascii(), "no control key for {ch:?}");
+        Key::Char(char::from(ch.to_ascii_uppercase() as u8 & 0x1F))
+    }
+}
+
+/// Something a key (or chord of keys) can do, when bound in a
+/// [`Keymap`](struct.Keymap.html).
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum Action {
+    /// Go to the beginning of the input.
+    BeginningOfLine,
+    /// Go to the end of the input.
+    EndOfLine,
+    /// Move the cursor back one character.
+    BackwardChar,
+    /// Move the cursor forward one character.
+    ForwardChar,
+    /// Delete the character before the cursor.
+    DeleteBackward,
+    /// Delete the character under the cursor.
+    DeleteForward,
+    /// Delete leftward from the cursor until reaching a `White_Space`
+    /// character.
+    DeleteWord,
+    /// Cut everything after the cursor.
+    KillToEnd,
+    /// Cut everything before the cursor.
+    KillToStart,
+    /// Paste the last text that was cut.
+    Yank,
+    /// Swap the character before the cursor with the one under it, and move
+    /// forward. At the end of the input, swap the last two characters.
+    TransposeChars,
+    /// Send the input.
+    Submit,
+    /// Discard the input, sending `Discarded`.
+    Discard,
+    /// Discard the input if there is any, or send `Finish` otherwise.
+    ClearOrFinish,
+    /// Clear the display.
+    ClearScreen,
+    /// Go to the previous line of history. (Requires the `history`
+    /// feature.)
+    HistoryPrevious,
+    /// Go to the next line of history. (Requires the `history` feature.)
+    HistoryNext,
+    /// Search backward through history, as you type, like Readline's
+    /// `reverse-search-history`. Pressing the same key again finds an
+    /// older match. Backspace shortens the search, control-G or Escape
+    /// gives up, and any other key stops searching and does what it
+    /// normally does. (Requires the `history` feature.)
+    HistorySearch,
+    /// Ask the [`Completor`](trait.Completor.html) to complete the input.
+    /// (Requires the `completion` feature.)
+    Complete,
+    /// Gracefully suspend ourselves, awaiting resumption by our parent
+    /// shell. (UNIX only.)
+    Suspend,
+    /// Edit the input in an external editor. (See
+    /// [`Output::set_editor_submits`][1].)
+    ///
+    /// [1]: struct.Output.html#method.set_editor_submits
+    EditInEditor,
+    /// Page back through the scrollback. (See
+    /// [the "Scrollback" section](index.html#scrollback).) Works even when
+    /// input isn't allowed.
+    ScrollbackPageUp,
+    /// Page forward through the scrollback.
+    ScrollbackPageDown,
+    /// Send a [`Response`](enum.Response.html). Create this with
+    /// [`respond`](#method.respond).
+    Respond(Arc<dyn Fn() -> Response + Send + Sync>),
+}
+
+impl Action {
+    /// An action that sends whatever `Response` the given function returns.
+    ///
+    /// ```rust
+    /// # use liso::{Action, Response};
+    /// let action = Action::respond(|| Response::Info);
+    /// ```
+    pub fn respond<F>(f: F) -> Action
+    where
+        F: Fn() -> Response + Send + Sync + 'static,
+    {
+        Action::Respond(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::BeginningOfLine => write!(f, "BeginningOfLine"),
+            Action::EndOfLine => write!(f, "EndOfLine"),
+            Action::BackwardChar => write!(f, "BackwardChar"),
+            Action::ForwardChar => write!(f, "ForwardChar"),
+            Action::DeleteBackward => write!(f, "DeleteBackward"),
+            Action::DeleteForward => write!(f, "DeleteForward"),
+            Action::DeleteWord => write!(f, "DeleteWord"),
+            Action::KillToEnd => write!(f, "KillToEnd"),
+            Action::KillToStart => write!(f, "KillToStart"),
+            Action::Yank => write!(f, "Yank"),
+            Action::TransposeChars => write!(f, "TransposeChars"),
+            Action::Submit => write!(f, "Submit"),
+            Action::Discard => write!(f, "Discard"),
+            Action::ClearOrFinish => write!(f, "ClearOrFinish"),
+            Action::ClearScreen => write!(f, "ClearScreen"),
+            Action::HistoryPrevious => write!(f, "HistoryPrevious"),
+            Action::HistoryNext => write!(f, "HistoryNext"),
+            Action::HistorySearch => write!(f, "HistorySearch"),
+            Action::Complete => write!(f, "Complete"),
+            Action::Suspend => write!(f, "Suspend"),
+            Action::EditInEditor => write!(f, "EditInEditor"),
+            Action::ScrollbackPageUp => write!(f, "ScrollbackPageUp"),
+            Action::ScrollbackPageDown => write!(f, "ScrollbackPageDown"),
+            Action::Respond(_) => write!(f, "Respond(..)"),
+        }
+    }
+}
+
+/// Which keys, and chords of keys, do what. Install one with
+/// [`Output::set_keymap`](struct.Output.html#method.set_keymap).
+///
+/// A chord is several keys pressed one after another, like control-X
+/// control-E. If a key could be the start of a chord, Liso waits briefly for
+/// the rest of it. If the rest doesn't come, the keys pressed so far do what
+/// they would do on their own.
+///
+/// Keys that aren't bound still do something: keys that produce printable
+/// characters insert them, and other control characters send
+/// [`Response::Unknown`](enum.Response.html#variant.Unknown).
+///
+/// ```rust,no_run
+/// # use liso::{Action, Key, Keymap, Response};
+/// # let io = liso::InputOutput::new();
+/// // The default bindings, except for Readline's control-T and control-R.
+/// let keymap = Keymap::new()
+///     .bind(&[Key::ctrl('T')], Action::TransposeChars)
+///     .bind(&[Key::ctrl('R')], Action::HistorySearch)
+///     .bind(&[Key::ctrl('X'), Key::ctrl('T')],
+///           Action::respond(|| Response::Info));
+/// io.set_keymap(keymap);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<Vec<Key>, Action>,
+}
+
+/// What a sequence of keys does in a `Keymap`.
+pub(crate) enum Lookup {
+    /// Whatever it's bound to.
+    Bound,
+    /// It's the start of a longer chord, so wait for more keys.
+    Prefix,
+    /// Nothing.
+    Unbound,
+}
+
+impl Keymap {
+    /// Liso's default bindings, which are based on a commonly-used subset of
+    /// the default GNU Readline bindings. (See the README for the list.)
+    pub fn new() -> Keymap {
+        use Action::*;
+        let ctrl = Key::ctrl;
+        let mut ret = Keymap::empty()
+            .bind(&[ctrl('A')], BeginningOfLine)
+            .bind(&[Key::Home], BeginningOfLine)
+            .bind(&[ctrl('B')], BackwardChar)
+            .bind(&[Key::Left], BackwardChar)
+            .bind(&[ctrl('C')], Action::respond(|| Response::Quit))
+            .bind(&[ctrl('D')], ClearOrFinish)
+            .bind(&[ctrl('E')], EndOfLine)
+            .bind(&[Key::End], EndOfLine)
+            .bind(&[ctrl('F')], ForwardChar)
+            .bind(&[Key::Right], ForwardChar)
+            .bind(&[ctrl('G')], Discard)
+            .bind(&[ctrl('K')], KillToEnd)
+            .bind(&[ctrl('L')], ClearScreen)
+            .bind(&[ctrl('T')], Action::respond(|| Response::Info))
+            .bind(&[ctrl('U')], KillToStart)
+            .bind(&[ctrl('W')], DeleteWord)
+            .bind(&[ctrl('X')], Action::respond(|| Response::Swap))
+            .bind(&[ctrl('X'), ctrl('E')], EditInEditor)
+            .bind(&[ctrl('Y')], Yank)
+            .bind(&[Key::Char('\t')], Complete)
+            .bind(&[Key::Char('\u{1B}')], Action::respond(|| Response::Escape))
+            .bind(&[ctrl('\\')], Action::respond(|| Response::Break))
+            .bind(&[Key::Char('\n')], Submit)
+            .bind(&[Key::Char('\r')], Submit)
+            .bind(&[Key::Char('\u{8}')], DeleteBackward)
+            .bind(&[Key::Char('\u{7F}')], DeleteBackward)
+            .bind(&[Key::Delete], DeleteForward)
+            .bind(&[Key::PageUp], ScrollbackPageUp)
+            .bind(&[Key::PageDown], ScrollbackPageDown);
+        if cfg!(feature = "history") {
+            ret = ret
+                .bind(&[ctrl('N')], HistoryNext)
+                .bind(&[Key::Down], HistoryNext)
+                .bind(&[ctrl('P')], HistoryPrevious)
+                .bind(&[Key::Up], HistoryPrevious);
+        }
+        if cfg!(unix) {
+            ret = ret.bind(&[ctrl('Z')], Suspend);
+        }
+        ret
+    }
+    /// A keymap with nothing bound at all. Printable characters will still
+    /// be inserted, but not even Return will do anything until you bind it.
+    pub fn empty() -> Keymap {
+        Keymap {
+            bindings: HashMap::new(),
+        }
+    }
+    /// Binds a key, or a chord of keys, to an action, replacing whatever it
+    /// was bound to before.
+    ///
+    /// Panics if `keys` is empty.
+    pub fn bind(mut self, keys: &[Key], action: Action) -> Keymap {
+        assert!(!keys.is_empty(), "can't bind an empty chord");
+        self.bindings.insert(keys.to_vec(), action);
+        self
+    }
+    /// Unbinds a key, or a chord of keys. (Chords that start with it are
+    /// left alone.)
+    pub fn unbind(mut self, keys: &[Key]) -> Keymap {
+        self.bindings.remove(keys);
+        self
+    }
+    /// Returns what the given key, or chord of keys, is bound to, if
+    /// anything.
+    pub fn get(&self, keys: &[Key]) -> Option<&Action> {
+        self.bindings.get(keys)
+    }
+    pub(crate) fn lookup(&self, keys: &[Key]) -> Lookup {
+        if self
+            .bindings
+            .keys()
+            .any(|x| x.len() > keys.len() && x.starts_with(keys))
+        {
+            return Lookup::Prefix;
+        }
+        match self.bindings.get(keys) {
+            Some(_) => Lookup::Bound,
+            None => Lookup::Unbound,
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn keymap_lookup() {
+        let keymap = Keymap::empty()
+            .bind(&[Key::ctrl('X')], Action::Yank)
+            .bind(&[Key::ctrl('X'), Key::ctrl('E')], Action::EditInEditor)
+            .bind(&[Key::ctrl('Q'), Key::Char('a')], Action::Submit);
+        assert!(matches!(keymap.lookup(&[Key::ctrl('X')]), Lookup::Prefix));
+        assert!(matches!(
+            keymap.lookup(&[Key::ctrl('X'), Key::ctrl('E')]),
+            Lookup::Bound
+        ));
+        assert!(matches!(
+            keymap.get(&[Key::ctrl('X'), Key::ctrl('E')]),
+            Some(Action::EditInEditor)
+        ));
+        assert!(matches!(
+            keymap.lookup(&[Key::ctrl('X'), Key::Up]),
+            Lookup::Unbound
+        ));
+        assert!(matches!(keymap.lookup(&[Key::ctrl('Q')]), Lookup::Prefix));
+        let keymap = keymap.unbind(&[Key::ctrl('X'), Key::ctrl('E')]);
+        assert!(matches!(keymap.lookup(&[Key::ctrl('X')]), Lookup::Bound));
+        assert!(matches!(keymap.get(&[Key::ctrl('X')]), Some(Action::Yank)));
+    }
+}
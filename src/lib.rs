@@ -52,6 +52,19 @@
 //! legitimate use may be to complain about an unknown control character. (See
 //! [`Response`](enum.Response.html) for an example of this use.)
 //!
+//! # Scrollback
+//!
+//! Liso keeps the most recent lines of output (1000, unless you change it
+//! with [`set_scrollback_limit()`][1]) so that the user can review them
+//! without relying on their terminal. Pressing PageUp covers the screen with
+//! a page of older output, PageUp and PageDown move through it, and any other
+//! key puts things back the way they were, including any input in progress.
+//! Output that arrives during review isn't lost; it's on the screen when
+//! review ends. This is handy on serial consoles, and in terminal
+//! multiplexers with unhelpful scrollback.
+//!
+//! [1]: struct.Output.html#method.set_scrollback_limit
+//!
 //! # Global
 //!
 //! If the `global` feature is enabled (which it is by default), you can call
@@ -94,6 +107,15 @@
 //! your new completor active. See the linked documentation for more
 //! information.
 //!
+//! # Logging
+//!
+//! If the `log` feature is enabled, [`Logger`](struct.Logger.html) lets
+//! Liso act as the backend for the [`log`](https://docs.rs/log) crate, so
+//! that log messages are printed as styled output instead of scrambling the
+//! prompt. Similarly, if the `tracing` feature is enabled,
+//! [`TracingLayer`](struct.TracingLayer.html) can be added to a
+//! [`tracing-subscriber`](https://docs.rs/tracing-subscriber) subscriber.
+//!
 //! # Pipe mode
 //!
 //! If *either* stdin or stdout is not a tty, *or* the `TERM` environment
@@ -122,8 +144,10 @@ use std::{
 #[cfg(not(feature = "global"))]
 use std::sync::atomic::{AtomicBool, Ordering};
 
+use std::sync::Arc;
+
 #[cfg(feature = "history")]
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::sync::{RwLock, RwLockReadGuard};
 
 #[cfg(feature = "completion")]
 use std::num::NonZeroU32;
@@ -131,11 +155,32 @@ use std::num::NonZeroU32;
 use bitflags::bitflags;
 use tokio::sync::mpsc as tokio_mpsc;
 
+mod cast;
+pub use cast::*;
+mod color;
+pub use color::*;
+mod command;
+pub use command::*;
+mod editor;
+mod keymap;
+pub use keymap::*;
 mod line;
 pub use line::*;
+mod progress;
+pub use progress::*;
+mod status;
+pub use status::*;
 mod term;
+mod theme;
+pub use theme::*;
+mod transcript;
+pub use transcript::*;
+mod updatable;
+pub use updatable::*;
 mod worker;
 use term::*;
+mod writer;
+pub use writer::*;
 #[cfg(unix)]
 #[path = "util/unix.rs"]
 mod util;
@@ -156,25 +201,20 @@ mod completion;
 #[cfg(feature = "completion")]
 pub use completion::*;
 
-#[cfg(feature = "capture-stderr")]
-mod stderr_capture;
+#[cfg(feature = "log")]
+mod logger;
+#[cfg(feature = "log")]
+pub use logger::*;
 
-use util::{enter_raw_mode, exit_raw_mode, InterruptibleStdinThread};
+#[cfg(feature = "tracing")]
+mod tracing_layer;
+#[cfg(feature = "tracing")]
+pub use tracing_layer::*;
 
-/// Internal use only: Representations for keyboard keys that are important to
-/// our operation but that don't produce text.
-enum KeyCode {
-    Home,
-    End,
-    PageUp,
-    PageDown,
-    Up,
-    Down,
-    Left,
-    Right,
-    Delete,
-    Backspace,
-}
+#[cfg(any(feature = "capture-stderr", feature = "capture-stdout"))]
+mod capture;
+
+use util::{enter_raw_mode, exit_raw_mode, InterruptibleStdinThread};
 
 /// This is the amount of time to wait after receiving an escape before we're
 /// sure we don't have an escape sequence on our hands.
@@ -194,6 +234,12 @@ enum KeyCode {
 /// in the input processing of their application followed by a beep … !!!
 const ESCAPE_DELAY: Duration = Duration::new(0, 1000000000 / 24);
 
+/// How long we wait, after a key that might start a chord (such as
+/// control-X), to see if the rest of the chord follows. If nothing does, the
+/// key is treated as having been pressed on its own. (Readline's default
+/// `keyseq-timeout` is the same.)
+const CHORD_DELAY: Duration = Duration::from_millis(500);
+
 /// We have to handle errors. There are two kinds we'll routinely face:
 ///
 /// - Error writing to `Stdout`
@@ -231,8 +277,11 @@ impl From<std_mpsc::RecvTimeoutError> for DummyError {
     }
 }
 
-/// Colors we support outputting. For compatibility, we only support the 3-bit
-/// ANSI colors.
+/// Colors we support outputting. The eight named colors are the 3-bit ANSI
+/// colors, which are supported nearly everywhere. `Indexed` and `Rgb` colors
+/// give access to the 256-color palette and to "truecolor" respectively, but
+/// many terminals don't support them. More kinds of color may be added in
+/// the future, so a `match` on a `Color` needs a wildcard arm.
 ///
 /// Here's a short list of reasons not to use color as the only source of
 /// certain information:
@@ -264,6 +313,7 @@ impl From<std_mpsc::RecvTimeoutError> for DummyError {
 /// [1]: http://no-color.org/
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 #[repr(u8)]
 pub enum Color {
     /// Absence of light. The color of space. (Some terminals will render this
@@ -283,38 +333,123 @@ pub enum Color {
     Magenta = 6,
     /// A (roughly) equal mix of all wavelengths of light.
     White = 7,
+    /// An entry in the xterm 256-color palette. 0 through 7 are the same as
+    /// the named colors, 8 through 15 are brighter versions of the same,
+    /// 16 through 231 are a 6×6×6 color cube, and 232 through 255 are a
+    /// grayscale ramp.
+    Indexed(u8) = 8,
+    /// An arbitrary 24-bit color, given as red, green, and blue components.
+    /// Terminals that don't support "truecolor" may show something quite
+    /// different.
+    Rgb(u8, u8, u8) = 9,
 }
 
+/// The named colors, in the order ANSI gives them. (Not quite the order of
+/// our discriminants; `Cyan` and `Magenta` are swapped.)
+const ANSI_COLORS: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+/// What the first sixteen entries of the xterm 256-color palette look like in
+/// xterm's default configuration.
+const XTERM_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
 impl Color {
-    // As an ANSI sequence to set the foreground color.
-    fn as_ansi_fg(&self) -> &'static str {
+    // Our index into the ANSI color order, for the named colors only.
+    fn ansi_index(&self) -> Option<u8> {
         match self {
-            Color::Black => "30",
-            Color::Red => "31",
-            Color::Green => "32",
-            Color::Yellow => "33",
-            Color::Blue => "34",
-            Color::Cyan => "35",
-            Color::Magenta => "36",
-            Color::White => "37",
+            Color::Black => Some(0),
+            Color::Red => Some(1),
+            Color::Green => Some(2),
+            Color::Yellow => Some(3),
+            Color::Blue => Some(4),
+            Color::Magenta => Some(5),
+            Color::Cyan => Some(6),
+            Color::White => Some(7),
+            Color::Indexed(_) | Color::Rgb(..) => None,
         }
     }
+    // As an ANSI sequence to set the foreground color.
+    fn as_ansi_fg(&self) -> Cow<'static, str> {
+        self.as_ansi_sgr(30, 90, 38)
+    }
     // As an ANSI sequence to set the background color.
-    fn as_ansi_bg(&self) -> &'static str {
-        match self {
-            Color::Black => "40",
-            Color::Red => "41",
-            Color::Green => "42",
-            Color::Yellow => "43",
-            Color::Blue => "44",
-            Color::Cyan => "45",
-            Color::Magenta => "46",
-            Color::White => "47",
+    fn as_ansi_bg(&self) -> Cow<'static, str> {
+        self.as_ansi_sgr(40, 100, 48)
+    }
+    // Common logic for the above. Uses the most widely supported form of SGR
+    // code that can express this color.
+    fn as_ansi_sgr(
+        &self,
+        base: u8,
+        bright: u8,
+        extended: u8,
+    ) -> Cow<'static, str> {
+        match *self {
+            Color::Indexed(x) if x < 8 => format!("{}", base + x).into(),
+            Color::Indexed(x) if x < 16 => {
+                format!("{}", bright + x - 8).into()
+            }
+            Color::Indexed(x) => format!("{extended};5;{x}").into(),
+            Color::Rgb(r, g, b) => format!("{extended};2;{r};{g};{b}").into(),
+            _ => format!("{}", base + self.ansi_index().unwrap()).into(),
+        }
+    }
+    // The (approximate) red, green, and blue components of this color.
+    fn as_rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Indexed(x) if x < 16 => XTERM_PALETTE[x as usize],
+            Color::Indexed(x) if x < 232 => {
+                let x = x - 16;
+                let level = |x: u8| if x == 0 { 0 } else { x * 40 + 55 };
+                (level(x / 36), level((x / 6) % 6), level(x % 6))
+            }
+            Color::Indexed(x) => {
+                let level = (x - 232) * 10 + 8;
+                (level, level, level)
+            }
+            _ => XTERM_PALETTE[self.ansi_index().unwrap() as usize],
+        }
+    }
+    // The named color that is closest to this one.
+    fn as_named(&self) -> Color {
+        match *self {
+            Color::Indexed(x) if x < 16 => ANSI_COLORS[(x % 8) as usize],
+            Color::Indexed(_) | Color::Rgb(..) => {
+                ANSI_COLORS
+                    [nearest_in_palette(self.as_rgb(), &XTERM_PALETTE[..8])]
+            }
+            _ => *self,
         }
     }
     // Convert to an Atari ST 16-color palette index (bright).
     fn as_atari16_bright(&self) -> u8 {
-        match self {
+        match self.as_named() {
             Color::Black => 8,
             Color::Red => 1,
             Color::Green => 2,
@@ -323,11 +458,12 @@ impl Color {
             Color::Cyan => 9,
             Color::Magenta => 12,
             Color::White => 0,
+            Color::Indexed(_) | Color::Rgb(..) => unreachable!(),
         }
     }
     // Convert to an Atari ST 16-color palette index (dim).
     fn as_atari16_dim(&self) -> u8 {
-        match self {
+        match self.as_named() {
             Color::Black => 15,
             Color::Red => 3,
             Color::Green => 5,
@@ -336,11 +472,12 @@ impl Color {
             Color::Cyan => 10,
             Color::Magenta => 14,
             Color::White => 7,
+            Color::Indexed(_) | Color::Rgb(..) => unreachable!(),
         }
     }
     // Convert to the nearest Atari ST 4-color palette index.
     fn as_atari4(&self) -> u8 {
-        match self {
+        match self.as_named() {
             Color::Black => 15,
             Color::Red => 1,
             Color::Green => 2,
@@ -349,10 +486,27 @@ impl Color {
             Color::Cyan => 2,
             Color::Magenta => 1,
             Color::White => 0,
+            Color::Indexed(_) | Color::Rgb(..) => unreachable!(),
         }
     }
 }
 
+/// Returns the index of the entry in `palette` that is nearest to `rgb`.
+fn nearest_in_palette(rgb: (u8, u8, u8), palette: &[(u8, u8, u8)]) -> usize {
+    let distance = |other: &(u8, u8, u8)| {
+        let dr = rgb.0 as i32 - other.0 as i32;
+        let dg = rgb.1 as i32 - other.1 as i32;
+        let db = rgb.2 as i32 - other.2 as i32;
+        dr * dr + dg * dg + db * db
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, x)| distance(x))
+        .map(|(i, _)| i)
+        .unwrap()
+}
+
 bitflags! {
     /// Styles we support outputting.
     ///
@@ -389,9 +543,32 @@ bitflags! {
         const REVERSE = 1 << 3;
         /// Prints in an italic font.
         const ITALIC = 1 << 4;
+        /// Prints with a line through the middle. Good for showing things
+        /// that have been deleted or are deprecated.
+        const STRIKETHROUGH = 1 << 5;
+        /// Blinks. Use sparingly, if at all. Many terminals ignore this, and
+        /// some users have it turned off on purpose.
+        const BLINK = 1 << 6;
+        /// Prints with a line above the text.
+        const OVERLINE = 1 << 7;
+        /// Prints with two lines under the baseline. Where supported, this
+        /// takes precedence over [`UNDERLINE`](#associatedconstant.UNDERLINE).
+        const DOUBLE_UNDERLINE = 1 << 8;
+        /// Prints the text invisibly. (It is still there, and can be selected
+        /// and copied.)
+        const CONCEAL = 1 << 9;
     }
 }
 
+impl Style {
+    /// Styles that draw lines, and are therefore visible even on blank
+    /// spaces.
+    pub(crate) const LINES: Style = Style::UNDERLINE
+        .union(Style::DOUBLE_UNDERLINE)
+        .union(Style::OVERLINE)
+        .union(Style::STRIKETHROUGH);
+}
+
 /// This struct contains all the methods that the
 /// [`OutputOnly`](struct.OutputOnly.html) and
 /// [`InputOutput`](struct.InputOutput.html) structs have in common. Any method
@@ -400,6 +577,28 @@ pub struct Output {
     tx: std_mpsc::Sender<Request>,
 }
 
+#[cfg(test)]
+impl Output {
+    /// An `Output` that isn't connected to Liso, and the other end of its
+    /// channel, so that tests can see what gets sent.
+    pub(crate) fn for_test() -> (Output, std_mpsc::Receiver<Request>) {
+        let (tx, rx) = std_mpsc::channel();
+        (Output { tx }, rx)
+    }
+}
+
+/// The lines that have been sent to the given channel as
+/// `Request::Output`. Panics if anything else was sent.
+#[cfg(test)]
+fn sent_lines(rx: &std_mpsc::Receiver<Request>) -> Vec<Line> {
+    rx.try_iter()
+        .map(|request| match request {
+            Request::Output(line) => line,
+            _ => panic!("unexpected request"),
+        })
+        .collect()
+}
+
 /// Sends output to the terminal. You can have more than one of these, shared
 /// freely among threads and tasks. Give one to every thread, task, or object
 /// that needs to produce output.
@@ -422,17 +621,39 @@ pub struct InputOutput {
 /// our caller isn't handling it correctly, and panic.
 const MAX_DEATH_COUNT: u32 = 9;
 
+/// The error returned by `Output` methods that do I/O through Liso, when
+/// Liso is no longer around to do it.
+fn liso_died() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Liso has died")
+}
+
 /// Something sent *to* the Liso thread.
 enum Request {
     /// Sent by `println`
     Output(Line),
-    /// Sent by `wrapln`
+    /// Sent by `wrapln` and `wrapln_with`
     #[cfg(feature = "wrap")]
-    OutputWrapped(Line),
+    OutputWrapped(Line, WrapOptions),
     /// Sent by `echoln`
     OutputEcho(Line),
+    /// Sent by `println_updatable`, and whenever an `UpdatableLine` is
+    /// changed
+    OutputUpdatable(u64, Line),
     /// Sent by `status`
     Status(Option<Line>),
+    /// Sent by `set_status_slot`
+    StatusSlot(String, Line),
+    /// Sent by `set_status_slot_layout`
+    StatusSlotLayout(String, i32, StatusAlign),
+    /// Sent by `remove_status_slot`
+    RemoveStatusSlot(String),
+    /// Sent when a `ProgressBar` or `Spinner` is created
+    Progress(String, Arc<ProgressShared>),
+    /// Sent when a `ProgressBar` or `Spinner` has changed since it was last
+    /// drawn
+    ProgressChanged,
+    /// Sent by `ProgressBar::finish` and `Spinner::finish`
+    RemoveProgress(String, Arc<ProgressShared>),
     /// Sent by `notice`
     Notice(Line, Duration),
     /// Sent by `prompt`
@@ -441,9 +662,8 @@ enum Request {
         input_allowed: bool,
         clear_input: bool,
     },
-    /// Sent by `suspend_and_run`
-    // TODO: FnOnce?
-    SuspendAndRun(Box<dyn FnMut() + Send>),
+    /// Sent by `suspend_and_run` (and friends)
+    SuspendAndRun(Box<dyn FnOnce() + Send>),
     /// Sent by `bell`
     Bell,
     /// Sent when we're cleaning up
@@ -459,7 +679,7 @@ enum Request {
     RawInput(String),
     /// Sent whenever a special key is received. That is, one that is not a
     /// control character and does not produce text.
-    Key(KeyCode),
+    Key(Key),
     /// Sent whenever a single character is received, and sending an entire
     /// `String` would be wasteful.
     Char(char),
@@ -473,9 +693,27 @@ enum Request {
     /// Sent when the `Completor` is to be replaced.
     #[cfg(feature = "completion")]
     SetCompletor(Option<Box<dyn Completor>>),
+    /// Sent by `set_theme`
+    SetTheme(Theme),
+    /// Sent by `set_scrollback_limit`
+    SetScrollbackLimit(usize),
+    /// Sent by `set_transcript`
+    SetTranscript(Option<Transcript>),
+    /// Sent by `set_cast_recording`
+    SetCastRecording(Option<CastRecording>),
     /// Sent when some captured stderr is received.
     #[cfg(feature = "capture-stderr")]
     StderrLine(String),
+    /// Sent by `set_stderr_decorator`
+    #[cfg(feature = "capture-stderr")]
+    SetStderrDecorator(Option<Box<dyn Fn(String) -> Option<Line> + Send>>),
+    /// Sent by `set_stderr_responses`
+    #[cfg(feature = "capture-stderr")]
+    SetStderrResponses(bool),
+    /// Sent by `set_editor_submits`
+    SetEditorSubmits(bool),
+    /// Sent by `set_keymap`
+    SetKeymap(Keymap),
 }
 
 /// Input received from the user, or a special condition. Returned by any of
@@ -574,11 +812,19 @@ pub enum Response {
     Break,
     /// Sent when the user presses Escape.
     Escape,
-    /// Sent when the user presses control-X.
+    /// Sent when the user presses control-X. (Liso waits briefly to see if
+    /// it's the start of a chord, such as control-X control-E, first.)
     Swap,
     /// Sent whenever `send_custom` is called. This can be used to interrupt
     /// the input thread when it's doing a `read_blocking` call.
     Custom(Box<dyn Any + Send>),
+    /// Sent for every line of captured stderr, instead of displaying it, if
+    /// [`set_stderr_responses`][1] has been turned on. Requires the
+    /// experimental `capture-stderr` feature.
+    ///
+    /// [1]: struct.Output.html#method.set_stderr_responses
+    #[cfg(feature = "capture-stderr")]
+    Stderr(String),
     /// Sent when the user presses an unknown control character with the given
     /// value (which will be between 0 and 31 inclusive).
     ///
@@ -600,6 +846,8 @@ impl Response {
             &Response::Input(_) => 10,
             &Response::Discarded(_) => 7,
             &Response::Custom(_) => 0,
+            #[cfg(feature = "capture-stderr")]
+            &Response::Stderr(_) => 0,
             &Response::Quit => 3,
             &Response::Finish => 4,
             &Response::Info => 20,
@@ -626,6 +874,16 @@ impl Output {
     {
         self.send(Request::Output(line.into()))
     }
+    /// Prints a (possibly styled) line of regular output to the screen, and
+    /// returns a handle that can be used to change it later, for example to
+    /// add "done" to the end of a "Downloading..." line. See
+    /// [`UpdatableLine`](struct.UpdatableLine.html) for details.
+    pub fn println_updatable<T>(&self, line: T) -> UpdatableLine
+    where
+        T: Into<Line>,
+    {
+        UpdatableLine::new(self.clone_output(), line.into())
+    }
     /// Prints a (possibly styled) line of regular output to the screen,
     /// wrapping it to the width of the terminal. Only available with the
     /// "wrap" feature, which is enabled by default.
@@ -638,7 +896,27 @@ impl Output {
     where
         T: Into<Line>,
     {
-        self.send(Request::OutputWrapped(line.into()))
+        self.send(Request::OutputWrapped(line.into(), WrapOptions::default()))
+    }
+    /// Prints a (possibly styled) line of regular output to the screen,
+    /// wrapping it to the width of the terminal according to the given
+    /// [`WrapOptions`](struct.WrapOptions.html). Use this if you want
+    /// continuation lines to be indented, e.g. to line them up under a
+    /// timestamp. Only available with the "wrap" feature, which is enabled by
+    /// default.
+    ///
+    /// If we're outputting to a pipe, the line isn't wrapped, but the
+    /// indentation is still applied.
+    ///
+    /// Note: As usual with `Output` methods, you can pass a
+    /// [`Line`](struct.Line.html), a plain `String`/`&str`, or a `Cow<str>`
+    /// here. See also the [`liso!`](macro.liso.html) macro.
+    #[cfg(feature = "wrap")]
+    pub fn wrapln_with<T>(&self, line: T, options: WrapOptions)
+    where
+        T: Into<Line>,
+    {
+        self.send(Request::OutputWrapped(line.into(), options))
     }
     /// Prints a (possibly styled) line of regular output to the screen, but
     /// only if we are being run interactively. Use this if you want to to echo
@@ -676,6 +954,116 @@ impl Output {
     pub fn remove_status(&self) {
         self.send(Request::Status(None))
     }
+    /// Sets the (possibly styled) text of the status slot with the given key,
+    /// creating the slot if it doesn't exist yet. Status slots are displayed
+    /// below the status line and above the prompt, one row each (or more, if
+    /// the text contains newlines). Rows too wide for the terminal are cut
+    /// short. (Does nothing in pipe mode.)
+    ///
+    /// Status slots let several parts of a program each have their own live
+    /// status row, without fighting over the single status line. Each slot
+    /// can be set or removed independently of the others, from any `Output`.
+    ///
+    /// New slots are left-aligned, and appear in order of their keys. Use
+    /// [`set_status_slot_layout`](#method.set_status_slot_layout) to change
+    /// this.
+    ///
+    /// ```rust,no_run
+    /// # use liso::{liso, StatusAlign};
+    /// # let io = liso::InputOutput::new();
+    /// io.set_status_slot_layout("clock", -1, StatusAlign::Right);
+    /// io.set_status_slot("net", liso!(fg = green, "net: connected"));
+    /// io.set_status_slot("clock", "12:34");
+    /// // ...
+    /// io.remove_status_slot("net");
+    /// ```
+    ///
+    /// Note: As usual with `Output` methods, you can pass a
+    /// [`Line`](struct.Line.html), a plain `String`/`&str`, or a `Cow<str>`
+    /// here. See also the [`liso!`](macro.liso.html) macro.
+    pub fn set_status_slot<K, T>(&self, key: K, line: T)
+    where
+        K: Into<String>,
+        T: Into<Line>,
+    {
+        self.send(Request::StatusSlot(key.into(), line.into()))
+    }
+    /// Sets where the status slot with the given key is displayed. Slots are
+    /// displayed in ascending `order`; slots with the same `order` are
+    /// displayed in order of their keys. `align` says where the text goes
+    /// within the slot's row. The default is an `order` of 0 and
+    /// [`StatusAlign::Left`](enum.StatusAlign.html#variant.Left).
+    ///
+    /// This can be called before the slot is first set, in which case the
+    /// layout will be used once it is. (Does nothing in pipe mode.)
+    pub fn set_status_slot_layout<K>(
+        &self,
+        key: K,
+        order: i32,
+        align: StatusAlign,
+    ) where
+        K: Into<String>,
+    {
+        self.send(Request::StatusSlotLayout(key.into(), order, align))
+    }
+    /// Removes the status slot with the given key, including its layout.
+    /// (Does nothing in pipe mode.)
+    pub fn remove_status_slot(&self, key: &str) {
+        self.send(Request::RemoveStatusSlot(key.to_string()))
+    }
+    /// Displays a progress bar in the status slot with the given key,
+    /// replacing anything that was already there, and returns a handle that
+    /// can be used to update it from any thread. `total` is the number of
+    /// units of work (bytes, files, ...) that need to be done. The bar fills
+    /// the width of the terminal, and uses the `progress` entry of the
+    /// current [`Theme`](struct.Theme.html). (Does nothing in pipe mode.)
+    ///
+    /// ```rust,no_run
+    /// # let io = liso::InputOutput::new();
+    /// # let files: Vec<String> = vec![];
+    /// let bar = io.progress_bar("copy", "Copying", files.len() as u64);
+    /// for file in files.iter() {
+    ///     // ... copy the file ...
+    ///     bar.inc(1);
+    /// }
+    /// bar.finish();
+    /// io.println(format!("Copied {} files.", files.len()));
+    /// ```
+    ///
+    /// See [`ProgressBar`](struct.ProgressBar.html) for more information, and
+    /// [`set_status_slot_layout`](#method.set_status_slot_layout) to change
+    /// where the bar appears.
+    pub fn progress_bar<K, T>(
+        &self,
+        key: K,
+        message: T,
+        total: u64,
+    ) -> ProgressBar
+    where
+        K: Into<String>,
+        T: Into<Line>,
+    {
+        ProgressBar::new(
+            self.clone_output(),
+            key.into(),
+            message.into(),
+            total,
+        )
+    }
+    /// Displays an animated spinner in the status slot with the given key,
+    /// replacing anything that was already there, and returns a handle that
+    /// can be used to update it from any thread. Use this instead of a
+    /// [`progress_bar`](#method.progress_bar) when there's no telling how
+    /// much work is left. (Does nothing in pipe mode.)
+    ///
+    /// See [`Spinner`](struct.Spinner.html) for more information.
+    pub fn spinner<K, T>(&self, key: K, message: T) -> Spinner
+    where
+        K: Into<String>,
+        T: Into<Line>,
+    {
+        Spinner::new(self.clone_output(), key.into(), message.into())
+    }
     /// Displays a (possibly styled) notice that temporarily replaces the
     /// prompt. The notice will disappear when the allotted time elapses, when
     /// the user presses any key, or when another notice is displayed,
@@ -752,23 +1140,99 @@ impl Output {
     /// the terminal, display the prompt, and continue as normal.
     ///
     /// Bear in mind that this will run in a separate thread, possibly after a
-    /// short delay. If you need to return a value, wait for completion, or
-    /// otherwise communicate with the main program, you should use the usual
-    /// inter-thread communication primitives, such as channels or atomics.
+    /// short delay. If you need to return a value or wait for completion, use
+    /// [`suspend_and_run_with_result`](#method.suspend_and_run_with_result).
+    /// If you need to otherwise communicate with the main program, you should
+    /// use the usual inter-thread communication primitives, such as channels
+    /// or atomics.
     ///
-    /// Note that you **cannot** use this to create a subprocess that will read
-    /// from stdin! Even though *output* is suspended, Liso will still be
-    /// reading from stdin in another thread, and thus, will be competing with
-    /// the subprocess for user input. (On sane UNIXes, this will result in
-    /// your program being suspended by your shell, and then misbehaving when
-    /// it resumes.) If you want to create a subprocess that can use stdin and
-    /// stdout, you'll have to write your own pipe handling based around Liso.
-    /// If you want to create a subprocess that can interactively use the
-    /// terminal, see the note in
-    /// [`blocking_die`])(struct.InputOutput.html#method.blocking_die).
-    pub fn suspend_and_run<F: 'static + FnMut() + Send>(&self, f: F) {
+    /// On UNIX and Windows, Liso stops reading from stdin until the function
+    /// returns, so it's safe to run a subprocess that interactively uses the
+    /// terminal (an editor, a pager, a shell...). The easiest way to do that
+    /// is with [`run_interactive`](#method.run_interactive). On other
+    /// platforms, Liso will still be reading from stdin in another thread,
+    /// and thus, will be competing with the subprocess for user input.
+    pub fn suspend_and_run<F: 'static + FnOnce() + Send>(&self, f: F) {
         self.send(Request::SuspendAndRun(Box::new(f)))
     }
+    /// Like [`suspend_and_run`](#method.suspend_and_run), but waits for the
+    /// function to finish, and returns whatever it returned.
+    ///
+    /// Don't call this from inside a function that's being run by
+    /// `suspend_and_run` (or this), or it will wait forever.
+    ///
+    /// Returns `None` if Liso has died, or dies before the function finishes.
+    /// (In that case, the function may not have been run at all.)
+    pub fn suspend_and_run_with_result<T, F>(&self, f: F) -> Option<T>
+    where
+        T: 'static + Send,
+        F: 'static + FnOnce() -> T + Send,
+    {
+        let (tx, rx) = std_mpsc::sync_channel(1);
+        self.tx
+            .send(Request::SuspendAndRun(Box::new(move || {
+                let _ = tx.send(f());
+            })))
+            .ok()?;
+        rx.recv().ok()
+    }
+    /// The `async` version of
+    /// [`suspend_and_run_with_result`](#method.suspend_and_run_with_result).
+    /// The function still runs in another thread; only the waiting is
+    /// asynchronous.
+    pub async fn suspend_and_run_with_result_async<T, F>(
+        &self,
+        f: F,
+    ) -> Option<T>
+    where
+        T: 'static + Send,
+        F: 'static + FnOnce() -> T + Send,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.tx
+            .send(Request::SuspendAndRun(Box::new(move || {
+                let _ = tx.send(f());
+            })))
+            .ok()?;
+        rx.await.ok()
+    }
+    /// Hands the terminal over to a command, for as long as it runs, and
+    /// returns how it exited. Liso is suspended (see
+    /// [`suspend_and_run`](#method.suspend_and_run)), the command runs with
+    /// the terminal in normal mode, and once it exits, Liso sets up the
+    /// terminal again and redraws everything.
+    ///
+    /// This is for commands that need to interact with the user directly,
+    /// such as editors, pagers, and `ssh`. If you just want to show a
+    /// command's output, use [`spawn_command`](#method.spawn_command)
+    /// instead.
+    ///
+    /// ```rust,no_run
+    /// # let io = liso::InputOutput::new();
+    /// let mut command = std::process::Command::new("less");
+    /// command.arg("README.md");
+    /// let status = io.run_interactive(command).unwrap();
+    /// ```
+    ///
+    /// This blocks until the command exits. If Liso has died, or dies before
+    /// the command exits, returns an error of kind `BrokenPipe`.
+    pub fn run_interactive(
+        &self,
+        mut command: std::process::Command,
+    ) -> std::io::Result<std::process::ExitStatus> {
+        self.suspend_and_run_with_result(move || command.status())
+            .unwrap_or_else(|| Err(liso_died()))
+    }
+    /// The `async` version of
+    /// [`run_interactive`](#method.run_interactive).
+    pub async fn run_interactive_async(
+        &self,
+        mut command: std::process::Command,
+    ) -> std::io::Result<std::process::ExitStatus> {
+        self.suspend_and_run_with_result_async(move || command.status())
+            .await
+            .unwrap_or_else(|| Err(liso_died()))
+    }
     /// Make a new `OutputOnly` that can also output to the terminal. The clone
     /// and the original can be stored in separate places, even in different
     /// threads or tasks. All output will go to the same terminal, without any
@@ -803,6 +1267,141 @@ impl Output {
     pub fn set_completor(&self, completor: Option<Box<dyn Completor>>) {
         self.send(Request::SetCompletor(completor))
     }
+    /// Provide a function that decides how each line of captured stderr is
+    /// displayed, or `None` to go back to the default. The function is given
+    /// the text of the line, and returns the (possibly styled) line to
+    /// display, or `None` to display nothing at all (handy for silencing a
+    /// noisy library). It's called on Liso's own thread, so it should be
+    /// quick.
+    ///
+    /// By default, lines are displayed with an `E: ` prefix, using the
+    /// `error` and `stderr` entries of the [`Theme`](struct.Theme.html).
+    /// Either way, the original text is what goes in a transcript. Requires
+    /// the experimental `capture-stderr` feature.
+    ///
+    /// ```rust,no_run
+    /// # use liso::{liso, Line};
+    /// # let io = liso::InputOutput::new();
+    /// io.set_stderr_decorator(Some(Box::new(|text: String| {
+    ///     if text.contains("deprecated") {
+    ///         None
+    ///     } else {
+    ///         let mut line = liso!(theme = "warning", "stderr: ", reset);
+    ///         line.add_ansi_text(&text);
+    ///         Some(line)
+    ///     }
+    /// })));
+    /// ```
+    #[cfg(feature = "capture-stderr")]
+    pub fn set_stderr_decorator(
+        &self,
+        decorator: Option<Box<dyn Fn(String) -> Option<Line> + Send>>,
+    ) {
+        self.send(Request::SetStderrDecorator(decorator))
+    }
+    /// If `true`, every line of captured stderr will be sent to you as a
+    /// [`Response::Stderr`](enum.Response.html#variant.Stderr), instead of
+    /// being displayed, so that you can handle it yourself. If `false` (the
+    /// default), lines are displayed. Requires the experimental
+    /// `capture-stderr` feature.
+    #[cfg(feature = "capture-stderr")]
+    pub fn set_stderr_responses(&self, enabled: bool) {
+        self.send(Request::SetStderrResponses(enabled))
+    }
+    /// Sets what happens to input that the user edits in an external editor
+    /// (by pressing control-X control-E). By default, when the editor exits,
+    /// the edited text replaces the input, for the user to look over before
+    /// sending it. Since the input is only one line, if there's more than one
+    /// line of text, the lines are joined together with spaces. If `submits`
+    /// is true, the text is sent right away instead, the same as if the user
+    /// had pressed Return at the end of each line.
+    ///
+    /// Either way, any control characters in the text (including tabs) are
+    /// replaced with spaces.
+    ///
+    /// The editor is the one named by the `VISUAL` environment variable, or
+    /// by `EDITOR` if that isn't set, or `vi` (`notepad` on Windows) if
+    /// neither is.
+    pub fn set_editor_submits(&self, submits: bool) {
+        self.send(Request::SetEditorSubmits(submits))
+    }
+    /// Replace the [`Keymap`](struct.Keymap.html) that decides what each key
+    /// does. (Does nothing in pipe mode.)
+    pub fn set_keymap(&self, keymap: Keymap) {
+        self.send(Request::SetKeymap(keymap))
+    }
+    /// Replace the [`Theme`](struct.Theme.html) used to display themed text.
+    /// The prompt and status line are restyled immediately, and all output
+    /// from now on will use the new theme. Output that has already been
+    /// printed stays the way it is.
+    pub fn set_theme(&self, theme: Theme) {
+        self.send(Request::SetTheme(theme))
+    }
+    /// Sets how many lines of output Liso keeps for the user to page back
+    /// through. (See [the "Scrollback" section](index.html#scrollback).) The
+    /// default is 1000. Setting it to 0 discards the scrollback, and stops
+    /// PageUp from doing anything. (Does nothing in pipe mode.)
+    pub fn set_scrollback_limit(&self, lines: usize) {
+        self.send(Request::SetScrollbackLimit(lines))
+    }
+    /// Runs a command, printing each line of its stdout and stderr as output
+    /// (parsing any ANSI escape sequences in it, the same as
+    /// [`Line::add_ansi_text`][1]). Its stdin is connected to nothing, since
+    /// Liso is using the real stdin. Returns a handle that can be used to
+    /// wait for it to exit.
+    ///
+    /// ```rust,no_run
+    /// # let io = liso::InputOutput::new();
+    /// let status = io
+    ///     .spawn_command(std::process::Command::new("ls"))
+    ///     .unwrap()
+    ///     .wait()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// To put a prefix on each line, or to be told when it exits without
+    /// waiting for it, use [`spawn_command_with`][2] instead.
+    ///
+    /// [1]: struct.Line.html#method.add_ansi_text
+    /// [2]: #method.spawn_command_with
+    pub fn spawn_command(
+        &self,
+        command: std::process::Command,
+    ) -> std::io::Result<CommandHandle> {
+        self.spawn_command_with(command, CommandOptions::default())
+    }
+    /// Runs a command the same way as
+    /// [`spawn_command`](#method.spawn_command), according to the given
+    /// [`CommandOptions`](struct.CommandOptions.html).
+    pub fn spawn_command_with(
+        &self,
+        command: std::process::Command,
+        options: CommandOptions,
+    ) -> std::io::Result<CommandHandle> {
+        command::spawn_command(self.clone_output(), command, options)
+    }
+    /// Returns an [`OutputWriter`](struct.OutputWriter.html), which
+    /// implements `std::io::Write` and prints every line written to it. Handy
+    /// for code that wants an `io::Write` to write to.
+    pub fn writer(&self) -> OutputWriter {
+        OutputWriter::new(self.clone_output())
+    }
+    /// Starts recording a [`Transcript`](struct.Transcript.html) of
+    /// everything that's printed or entered from now on, or stops recording
+    /// if `None`. Replaces (and closes) any transcript that was already being
+    /// recorded. Works in pipe mode too, although only output and input are
+    /// recorded there, since nothing else is displayed.
+    pub fn set_transcript(&self, transcript: Option<Transcript>) {
+        self.send(Request::SetTranscript(transcript))
+    }
+    /// Starts recording a [`CastRecording`](struct.CastRecording.html) of
+    /// everything that's drawn on the terminal from now on, or stops
+    /// recording if `None`. Replaces (and closes) any cast that was already
+    /// being recorded. The prompt and status are redrawn right away, so that
+    /// the recording starts out showing them. (Does nothing in pipe mode.)
+    pub fn set_cast_recording(&self, recording: Option<CastRecording>) {
+        self.send(Request::SetCastRecording(recording))
+    }
 }
 
 impl Drop for InputOutput {
@@ -814,8 +1413,8 @@ impl Drop for InputOutput {
         self.actually_blocking_die();
         #[cfg(not(feature = "global"))]
         LISO_IS_ACTIVE.store(false, Ordering::Release);
-        #[cfg(feature = "capture-stderr")]
-        stderr_capture::wait_until_not_captured();
+        #[cfg(any(feature = "capture-stderr", feature = "capture-stdout"))]
+        capture::wait_until_not_captured_all();
     }
 }
 
@@ -1104,20 +1703,6 @@ impl Clone for OutputOnly {
     }
 }
 
-#[cfg(feature = "wrap")]
-fn convert_subset_slice_to_range(outer: &str, inner: &str) -> (usize, usize) {
-    if inner.is_empty() {
-        return (0, 0);
-    }
-    let outer_start = outer.as_ptr() as usize;
-    let outer_end = outer_start.checked_add(outer.len()).unwrap();
-    let inner_start = inner.as_ptr() as usize;
-    let inner_end = inner_start.checked_add(inner.len()).unwrap();
-    assert!(inner_start >= outer_start);
-    assert!(inner_end <= outer_end);
-    (inner_start - outer_start, inner_end - outer_start)
-}
-
 /// Produce an `Option<Color>` from a name or expression. For internal use by
 /// the [`liso!`](macro.liso.html) and [`liso_add!`](macro.liso_add.html)
 /// macros.
@@ -1211,6 +1796,68 @@ macro_rules! liso_add {
     ($line:ident, reset) => {
         $line.reset_all();
     };
+    // Set fg/bg color from the extended palettes
+    // (`fg` | `bg`) `=` (`rgb(`<R>`,`<G>`,`<B>`)` | `indexed(`<I>`)`)
+    ($line:ident, fg = rgb($r:expr, $g:expr, $b:expr), $($rest:tt)*) => {
+        $line.set_fg_color(Some($crate::Color::Rgb($r, $g, $b)));
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, fg = rgb($r:expr, $g:expr, $b:expr)) => {
+        $line.set_fg_color(Some($crate::Color::Rgb($r, $g, $b)));
+    };
+    ($line:ident, fg = indexed($i:expr), $($rest:tt)*) => {
+        $line.set_fg_color(Some($crate::Color::Indexed($i)));
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, fg = indexed($i:expr)) => {
+        $line.set_fg_color(Some($crate::Color::Indexed($i)));
+    };
+    ($line:ident, bg = rgb($r:expr, $g:expr, $b:expr), $($rest:tt)*) => {
+        $line.set_bg_color(Some($crate::Color::Rgb($r, $g, $b)));
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, bg = rgb($r:expr, $g:expr, $b:expr)) => {
+        $line.set_bg_color(Some($crate::Color::Rgb($r, $g, $b)));
+    };
+    ($line:ident, bg = indexed($i:expr), $($rest:tt)*) => {
+        $line.set_bg_color(Some($crate::Color::Indexed($i)));
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, bg = indexed($i:expr)) => {
+        $line.set_bg_color(Some($crate::Color::Indexed($i)));
+    };
+    // Set or clear a theme entry
+    // `theme` `=` (`none` | <name>)
+    ($line:ident, theme = none, $($rest:tt)*) => {
+        $line.set_theme(None);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, theme = none) => {
+        $line.set_theme(None);
+    };
+    ($line:ident, theme = $name:expr, $($rest:tt)*) => {
+        $line.set_theme(Some(&$name));
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, theme = $name:expr) => {
+        $line.set_theme(Some(&$name));
+    };
+    // Set or clear a link
+    // `link` `=` (`none` | <url>)
+    ($line:ident, link = none, $($rest:tt)*) => {
+        $line.set_link(None);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, link = none) => {
+        $line.set_link(None);
+    };
+    ($line:ident, link = $url:expr, $($rest:tt)*) => {
+        $line.set_link(Some(&$url));
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, link = $url:expr) => {
+        $line.set_link(Some(&$url));
+    };
     // Set fg/bg color
     // (`fg` | `bg`) `=` <COLOR>
     ($line:ident, fg = $color:tt, $($rest:tt)*) => {
@@ -1248,7 +1895,8 @@ macro_rules! liso_add {
         $crate::liso_add!($line, $($rest)*);
     };
     // SET styles
-    // `bold` | `dim` | `underline` | `inverse` | `reverse` | `italic`
+    // `bold` | `dim` | `underline` | `inverse` | `reverse` | `italic` |
+    // `strikethrough` | `blink` | `overline` | `double_underline` | `conceal`
     ($line:ident, bold $($rest:tt)*) => {
         $line.set_style($crate::Style::BOLD);
         $crate::liso_add!($line, $($rest)*);
@@ -1273,8 +1921,30 @@ macro_rules! liso_add {
         $line.set_style($crate::Style::ITALIC);
         $crate::liso_add!($line, $($rest)*);
     };
+    ($line:ident, strikethrough $($rest:tt)*) => {
+        $line.set_style($crate::Style::STRIKETHROUGH);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, blink $($rest:tt)*) => {
+        $line.set_style($crate::Style::BLINK);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, overline $($rest:tt)*) => {
+        $line.set_style($crate::Style::OVERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, double_underline $($rest:tt)*) => {
+        $line.set_style($crate::Style::DOUBLE_UNDERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, conceal $($rest:tt)*) => {
+        $line.set_style($crate::Style::CONCEAL);
+        $crate::liso_add!($line, $($rest)*);
+    };
     // ADD styles
-    // `+` (`bold` | `dim` | `underline` | `inverse` | `reverse` | `italic`)
+    // `+` (`bold` | `dim` | `underline` | `inverse` | `reverse` | `italic`
+    //   | `strikethrough` | `blink` | `overline` | `double_underline`
+    //   | `conceal`)
     ($line:ident, +bold $($rest:tt)*) => {
         $line.activate_style($crate::Style::BOLD);
         $crate::liso_add!($line, $($rest)*);
@@ -1299,8 +1969,30 @@ macro_rules! liso_add {
         $line.activate_style($crate::Style::ITALIC);
         $crate::liso_add!($line, $($rest)*);
     };
+    ($line:ident, +strikethrough $($rest:tt)*) => {
+        $line.activate_style($crate::Style::STRIKETHROUGH);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, +blink $($rest:tt)*) => {
+        $line.activate_style($crate::Style::BLINK);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, +overline $($rest:tt)*) => {
+        $line.activate_style($crate::Style::OVERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, +double_underline $($rest:tt)*) => {
+        $line.activate_style($crate::Style::DOUBLE_UNDERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, +conceal $($rest:tt)*) => {
+        $line.activate_style($crate::Style::CONCEAL);
+        $crate::liso_add!($line, $($rest)*);
+    };
     // REMOVE styles
-    // `-` (`bold` | `dim` | `underline` | `inverse` | `reverse` | `italic`)
+    // `-` (`bold` | `dim` | `underline` | `inverse` | `reverse` | `italic`
+    //   | `strikethrough` | `blink` | `overline` | `double_underline`
+    //   | `conceal`)
     ($line:ident, -bold $($rest:tt)*) => {
         $line.deactivate_style($crate::Style::BOLD);
         $crate::liso_add!($line, $($rest)*);
@@ -1325,8 +2017,30 @@ macro_rules! liso_add {
         $line.deactivate_style($crate::Style::ITALIC);
         $crate::liso_add!($line, $($rest)*);
     };
+    ($line:ident, -strikethrough $($rest:tt)*) => {
+        $line.deactivate_style($crate::Style::STRIKETHROUGH);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, -blink $($rest:tt)*) => {
+        $line.deactivate_style($crate::Style::BLINK);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, -overline $($rest:tt)*) => {
+        $line.deactivate_style($crate::Style::OVERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, -double_underline $($rest:tt)*) => {
+        $line.deactivate_style($crate::Style::DOUBLE_UNDERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, -conceal $($rest:tt)*) => {
+        $line.deactivate_style($crate::Style::CONCEAL);
+        $crate::liso_add!($line, $($rest)*);
+    };
     // TOGGLE styles
-    // `^` (`bold` | `dim` | `underline` | `inverse` | `reverse` | `italic`)
+    // `^` (`bold` | `dim` | `underline` | `inverse` | `reverse` | `italic`
+    //   | `strikethrough` | `blink` | `overline` | `double_underline`
+    //   | `conceal`)
     ($line:ident, ^bold $($rest:tt)*) => {
         $line.toggle_style($crate::Style::BOLD);
         $crate::liso_add!($line, $($rest)*);
@@ -1351,6 +2065,26 @@ macro_rules! liso_add {
         $line.toggle_style($crate::Style::ITALIC);
         $crate::liso_add!($line, $($rest)*);
     };
+    ($line:ident, ^strikethrough $($rest:tt)*) => {
+        $line.toggle_style($crate::Style::STRIKETHROUGH);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, ^blink $($rest:tt)*) => {
+        $line.toggle_style($crate::Style::BLINK);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, ^overline $($rest:tt)*) => {
+        $line.toggle_style($crate::Style::OVERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, ^double_underline $($rest:tt)*) => {
+        $line.toggle_style($crate::Style::DOUBLE_UNDERLINE);
+        $crate::liso_add!($line, $($rest)*);
+    };
+    ($line:ident, ^conceal $($rest:tt)*) => {
+        $line.toggle_style($crate::Style::CONCEAL);
+        $crate::liso_add!($line, $($rest)*);
+    };
     // Interpret ANSI sequences
     // `ansi` <text>
     ($line:ident, ansi $expr:expr, $($rest:tt)*) => {
@@ -1399,16 +2133,30 @@ macro_rules! liso_add {
 ///   Set the foreground color.
 /// - `bg = <color>`  
 ///   Set the background color.
+/// - `fg = rgb(<r>, <g>, <b>)`, `bg = rgb(<r>, <g>, <b>)`  
+///   Set the foreground or background to a 24-bit color.
+/// - `fg = indexed(<i>)`, `bg = indexed(<i>)`  
+///   Set the foreground or background to a color from the 256-color
+///   palette.
+/// - `theme = <name>`  
+///   Style the following text using the named entry from the current
+///   [`Theme`](struct.Theme.html).
+/// - `theme = none`  
+///   Stop using a theme entry.
+/// - `link = <url>`  
+///   Make the following text a hyperlink to the given URL.
+/// - `link = none`  
+///   Stop linking.
 /// - `reset`  
-///   Clear all style and color information.
+///   Clear all style, color, and theme information.
 /// - `ansi <text>`
 ///   Text to output, with interpretation of some ANSI escape sequences found
 ///   in the text.
 /// - `<text>`  
 ///   Text to output.
 ///
-/// You have to put a comma after `fg = ...`, `bg = ...`, `reset`, and text.
-/// They are optional everywhere else.
+/// You have to put a comma after `fg = ...`, `bg = ...`, `theme = ...`,
+/// `link = ...`, `reset`, and text. They are optional everywhere else.
 ///
 /// ```rust
 /// # use liso::liso;
@@ -1418,7 +2166,9 @@ macro_rules! liso_add {
 ///                  "really", plain, " bad idea!");
 /// ```
 ///
-/// `<style>` may be `bold`, `dim`, `inverse`/`reverse`, `italic`, or `plain`.
+/// `<style>` may be `bold`, `dim`, `underline`, `double_underline`,
+/// `inverse`/`reverse`, `italic`, `strikethrough`, `blink`, `overline`,
+/// `conceal`, or `plain`.
 /// `<color>` may be the actual name of a [`Color`](enum.Color.html), the
 /// lowercase equivalent, `None`/`none`, or any expression evaluating to an
 /// `Option<Color>`. `<text>` may be anything that you could pass directly to
@@ -1,6 +1,17 @@
 use super::*;
 
 mod add_ansi;
+mod html;
+pub use html::*;
+mod markup;
+pub use markup::*;
+mod slice;
+mod to_ansi;
+pub(crate) use to_ansi::*;
+#[cfg(feature = "wrap")]
+mod wrap;
+#[cfg(feature = "wrap")]
+pub use wrap::*;
 
 /// An individual styled span within a line.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -12,12 +23,42 @@ pub(crate) struct LineElement {
     pub(crate) fg: Option<Color>,
     /// The background color (if any).
     pub(crate) bg: Option<Color>,
+    /// The URL this span links to (if any).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) link: Option<String>,
+    /// The name of the [`Theme`](struct.Theme.html) entry to style this span
+    /// with when it's displayed (if any).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) theme: Option<String>,
     /// The start (inclusive) and end (exclusive) range of text within the
     /// parent `Line` to which these attributes apply.
     pub(crate) start: usize,
     pub(crate) end: usize,
 }
 
+impl LineElement {
+    /// An empty, unstyled element starting at the given position.
+    fn new(start: usize) -> LineElement {
+        LineElement {
+            style: Style::PLAIN,
+            fg: None,
+            bg: None,
+            link: None,
+            theme: None,
+            start,
+            end: start,
+        }
+    }
+    /// True if the two elements display their text the same way.
+    fn same_attrs_as(&self, other: &LineElement) -> bool {
+        self.style == other.style
+            && self.fg == other.fg
+            && self.bg == other.bg
+            && self.link == other.link
+            && self.theme == other.theme
+    }
+}
+
 /// This is a line of text, with optional styling information, ready for
 /// display. The [`liso!`](macro.liso.html) macro is extremely convenient for
 /// building these. You can also pass a `String`, `&str`, or `Cow<str>` to
@@ -69,13 +110,9 @@ impl Line {
             // The line didn't have any text or elements yet.
             match self.elements.last_mut() {
                 None => {
-                    self.elements.push(LineElement {
-                        style: Style::PLAIN,
-                        fg: None,
-                        bg: None,
-                        start: 0,
-                        end: i.len(),
-                    });
+                    let mut element = LineElement::new(0);
+                    element.end = i.len();
+                    self.elements.push(element);
                 }
                 Some(x) => {
                     assert_eq!(x.start, 0);
@@ -94,6 +131,54 @@ impl Line {
             endut.end = end;
         }
     }
+    // The attributes that will apply to newly-added text.
+    fn current_attrs(&self) -> LineElement {
+        match self.elements.last() {
+            None => LineElement::new(self.text.len()),
+            Some(x) => LineElement {
+                start: self.text.len(),
+                end: self.text.len(),
+                ..x.clone()
+            },
+        }
+    }
+    // The attributes of the text at the given byte index.
+    fn attrs_at(&self, index: usize) -> LineElement {
+        self.elements
+            .iter()
+            .find(|x| x.start <= index && index < x.end)
+            .cloned()
+            .unwrap_or_else(|| self.current_attrs())
+    }
+    // Makes newly-added text use the same attributes as the given element.
+    fn restore_attrs(&mut self, attrs: &LineElement) -> &mut Line {
+        self.change_attrs(|x| {
+            *x = LineElement {
+                start: x.start,
+                end: x.end,
+                ..attrs.clone()
+            }
+        })
+    }
+    // Changes the attributes that will apply to newly-added text.
+    fn change_attrs<F>(&mut self, f: F) -> &mut Line
+    where
+        F: FnOnce(&mut LineElement),
+    {
+        // (if there are no elements yet, this makes one)
+        let mut nu = self.current_attrs();
+        f(&mut nu);
+        match self.elements.last_mut() {
+            // case 2: no change to attributes
+            Some(x) if nu.same_attrs_as(x) => (),
+            // case 3: last element doesn't have text yet.
+            Some(x) if x.start == x.end => *x = nu,
+            // case 1: no elements yet, or...
+            // case 4: an element with text is here.
+            _ => self.elements.push(nu),
+        }
+        self
+    }
     /// Adds additional text to the `Line` using the currently-active
     /// [`Style`][1] and [`Color`][2]s..
     ///
@@ -170,35 +255,7 @@ impl Line {
     ///
     /// [1]: struct.Style.html
     pub fn set_style(&mut self, nu: Style) -> &mut Line {
-        let (fg, bg) = match self.elements.last_mut() {
-            // case 1: no elements yet, make one.
-            None => {
-                // (fall through)
-                (None, None)
-            }
-            Some(x) => {
-                // case 2: no change to attributes
-                if x.style == nu {
-                    return self;
-                }
-                // case 3: last element doesn't have text yet.
-                else if x.start == x.end {
-                    x.style = nu;
-                    return self;
-                }
-                (x.fg, x.bg)
-            }
-        };
-        // (case 1 fall through, or...)
-        // case 4: an element with text is here.
-        self.elements.push(LineElement {
-            style: nu,
-            fg,
-            bg,
-            start: self.text.len(),
-            end: self.text.len(),
-        });
-        self
+        self.change_attrs(|x| x.style = nu)
     }
     /// Toggle every given [`Style`][1].
     ///
@@ -267,51 +324,118 @@ impl Line {
         fg: Option<Color>,
         bg: Option<Color>,
     ) -> &mut Line {
-        let prev_style = match self.elements.last_mut() {
-            // case 1: no elements yet, make one.
-            None => Style::PLAIN,
-            Some(x) => {
-                // case 2: no change to style
-                if x.fg == fg && x.bg == bg {
-                    return self;
-                }
-                // case 3: last element doesn't have text yet.
-                else if x.start == x.end {
-                    x.fg = fg;
-                    x.bg = bg;
-                    return self;
+        self.change_attrs(|x| {
+            x.fg = fg;
+            x.bg = bg;
+        })
+    }
+    /// Gets the URL that newly-added text will link to, if any.
+    pub fn get_link(&self) -> Option<&str> {
+        self.elements.last().and_then(|x| x.link.as_deref())
+    }
+    /// Makes newly-added text link to the given URL, or stops linking if
+    /// `None`. Style and color information are unaffected.
+    ///
+    /// On terminals that support it, the text will be clickable. (This uses
+    /// the "OSC 8" escape sequence, which many modern terminals support.) On
+    /// other terminals, only the text will be shown.
+    ///
+    /// ```rust
+    /// # use liso::{liso, Line};
+    /// let mut line = Line::new();
+    /// line.add_text("See ");
+    /// line.set_link(Some("https://example.com/"));
+    /// line.add_text("the manual");
+    /// line.set_link(None);
+    /// line.add_text(" for details.");
+    /// assert_eq!(line, liso!("See ", link = "https://example.com/",
+    ///                        "the manual", link = none, " for details."));
+    /// ```
+    pub fn set_link(&mut self, nu: Option<&str>) -> &mut Line {
+        self.change_attrs(|x| x.link = nu.map(str::to_string))
+    }
+    /// Gets the name of the [`Theme`][1] entry that newly-added text will be
+    /// styled with, if any.
+    ///
+    /// [1]: struct.Theme.html
+    pub fn get_theme(&self) -> Option<&str> {
+        self.elements.last().and_then(|x| x.theme.as_deref())
+    }
+    /// Makes newly-added text use the named entry from the current
+    /// [`Theme`][1], or stops using one if `None`. The name is looked up when
+    /// the line is displayed, so changing the theme with
+    /// [`Output::set_theme`][2] will restyle it. The entry's style is added to
+    /// the line's own [`Style`][3], and its colors are used only where the
+    /// line doesn't have its own [`Color`][4]s.
+    ///
+    /// ```rust
+    /// # use liso::{liso, Line, Theme};
+    /// let mut line = Line::new();
+    /// line.set_theme(Some("error"));
+    /// line.add_text("Error:");
+    /// line.set_theme(None);
+    /// line.add_text(" file not found");
+    /// assert_eq!(line, liso!(theme = "error", "Error:", theme = none,
+    ///                        " file not found"));
+    /// assert_eq!(line.resolve_theme(&Theme::default()),
+    ///            liso!(bold, fg = red, "Error:", reset, " file not found"));
+    /// ```
+    ///
+    /// [1]: struct.Theme.html
+    /// [2]: struct.Output.html#method.set_theme
+    /// [3]: struct.Style.html
+    /// [4]: enum.Color.html
+    pub fn set_theme(&mut self, nu: Option<&str>) -> &mut Line {
+        self.change_attrs(|x| x.theme = nu.map(str::to_string))
+    }
+    /// Returns a copy of this `Line` with every [`Theme`][1] entry it uses
+    /// replaced by the actual styles and colors from the given `Theme`.
+    /// Entries that aren't in the `Theme` are ignored. You'll want this before
+    /// calling [`to_ansi_string`][2] or [`to_html`][3] on a themed line.
+    ///
+    /// [1]: struct.Theme.html
+    /// [2]: #method.to_ansi_string
+    /// [3]: #method.to_html
+    pub fn resolve_theme(&self, theme: &Theme) -> Line {
+        self.themed(theme).into_owned()
+    }
+    // Like `resolve_theme`, but doesn't copy a line that has no themes.
+    pub(crate) fn themed(&self, theme: &Theme) -> Cow<'_, Line> {
+        if self.elements.iter().all(|x| x.theme.is_none()) {
+            return Cow::Borrowed(self);
+        }
+        let mut ret = self.clone();
+        for element in ret.elements.iter_mut() {
+            if let Some(name) = element.theme.take() {
+                if let Some(entry) = theme.get(&name) {
+                    element.style |= entry.style;
+                    element.fg = element.fg.or(entry.fg);
+                    element.bg = element.bg.or(entry.bg);
                 }
-                x.style
             }
-        };
-        // (case 1 fall through, or...)
-        // case 3: an element with text is here.
-        self.elements.push(LineElement {
-            style: prev_style,
-            fg,
-            bg,
-            start: self.text.len(),
-            end: self.text.len(),
-        });
-        self
+        }
+        Cow::Owned(ret)
     }
-    /// Reset ALL [`Style`][1] and [`Color`][2] information to default.
-    /// Equivalent to:
+    /// Reset ALL [`Style`][1] and [`Color`][2] information to default, and
+    /// stop using any [`Theme`][3] entry. Equivalent to:
     ///
     /// ```
     /// # use liso::Style;
     /// # let mut line = liso::Line::new();
-    /// # liso::liso_add!(line, fg=green, bg=red, underline);
-    /// line.set_style(Style::PLAIN).set_colors(None, None);
-    /// # assert_eq!(line, liso::liso!(plain, fg=none, bg=none));
+    /// # liso::liso_add!(line, fg=green, bg=red, underline, theme="muted");
+    /// line.set_style(Style::PLAIN).set_colors(None, None).set_theme(None);
+    /// # assert_eq!(line, liso::liso!(plain, fg=none, bg=none, theme=none));
     /// ```
     ///
-    /// (In fact, that is the body of this function.)
+    /// (In fact, that is the body of this function.) Links are unaffected.
     ///
     /// [1]: struct.Style.html
     /// [2]: enum.Color.html
+    /// [3]: struct.Theme.html
     pub fn reset_all(&mut self) -> &mut Line {
-        self.set_style(Style::PLAIN).set_colors(None, None)
+        self.set_style(Style::PLAIN)
+            .set_colors(None, None)
+            .set_theme(None)
     }
     /// Returns true if this line contains no text. (It may yet contain some
     /// [`Style`][1] or [`Color`][2] information.)
@@ -338,7 +462,8 @@ impl Line {
     pub fn chars(&self) -> LineCharIterator<'_> {
         LineCharIterator::new(self)
     }
-    /// Add a linebreak and then clear [`Style`][1] and [`Color`][2]s.
+    /// Add a linebreak and then clear [`Style`][1], [`Color`][2]s, and
+    /// [`Theme`][3] entry.
     ///
     /// Equivalent to:
     ///
@@ -349,6 +474,7 @@ impl Line {
     /// line.add_text("\n");
     /// line.set_style(Style::empty());
     /// line.set_colors(None, None);
+    /// line.set_theme(None);
     /// # assert_eq!(line, liso::liso!(fg=green, bg=red, underline,
     /// #   "\n", reset));
     /// ```
@@ -357,13 +483,16 @@ impl Line {
     ///
     /// [1]: struct.Style.html
     /// [2]: enum.Color.html
+    /// [3]: struct.Theme.html
     pub fn reset_and_break(&mut self) {
         self.add_text("\n");
         self.set_style(Style::empty());
         self.set_colors(None, None);
+        self.set_theme(None);
     }
-    /// Append another Line to ourselves, including [`Style`][1] and
-    /// [`Color`][2] information. You may want to [`reset_and_break`][3] first.
+    /// Append another Line to ourselves, including [`Style`][1],
+    /// [`Color`][2], link, and theme information. You may want to
+    /// [`reset_and_break`][3] first.
     ///
     /// [1]: struct.Style.html
     /// [2]: enum.Color.html
@@ -372,6 +501,8 @@ impl Line {
         for element in other.elements.iter() {
             self.set_style(element.style);
             self.set_colors(element.fg, element.bg);
+            self.set_link(element.link.as_deref());
+            self.set_theme(element.theme.as_deref());
             self.add_text(&other.text[element.start..element.end]);
         }
     }
@@ -386,98 +517,7 @@ impl Line {
     /// user's terminal.
     #[cfg(feature = "wrap")]
     pub fn wrap_to_width(&mut self, width: usize) {
-        assert!(width > 0);
-        let newline_positions: Vec<usize> = self
-            .text
-            .chars()
-            .enumerate()
-            .filter_map(|(n, c)| if c == '\n' { Some(n) } else { None })
-            .chain(Some(self.text.len()))
-            .collect();
-        let start_iter = newline_positions
-            .iter()
-            .rev()
-            .skip(1)
-            .map(|x| *x + 1)
-            .chain(Some(0usize));
-        let end_iter = newline_positions.iter().rev();
-        for (start, &end) in start_iter.zip(end_iter) {
-            if start >= end {
-                continue;
-            }
-            let wrap_vec = textwrap::wrap(&self.text[start..end], width);
-            let mut edit_vec = Vec::with_capacity(wrap_vec.len());
-            let mut cur_end = start;
-            for el in wrap_vec.into_iter() {
-                // We're pretty sure we didn't use any features that would require
-                // an owned Cow. In fact, if we're wrong, the whole feature won't
-                // work.
-                let slice = match el {
-                    Cow::Borrowed(x) => x,
-                    Cow::Owned(_) => {
-                        panic!("We needed textwrap to do borrows only!")
-                    }
-                };
-                let (start, end) =
-                    convert_subset_slice_to_range(&self.text, slice);
-                debug_assert!(start <= end);
-                if start == end {
-                    continue;
-                }
-                assert!(start >= cur_end);
-                if start != 0 {
-                    edit_vec.push(cur_end..start);
-                }
-                cur_end = end;
-            }
-            for range in edit_vec.into_iter().rev() {
-                if range.start > 0
-                    && self.text.as_bytes()[range.start - 1] == b'\n'
-                {
-                    continue;
-                }
-                self.erase_and_insert_newline(range);
-            }
-        }
-    }
-    // Internal use only.
-    #[cfg(feature = "wrap")]
-    fn erase_and_insert_newline(&mut self, range: std::ops::Range<usize>) {
-        let delta_bytes = 1 - (range.end as isize - range.start as isize);
-        self.text.replace_range(range.clone(), "\n");
-        let mut elements_len = self.elements.len();
-        let mut i = self.elements.len();
-        loop {
-            if i == 0 {
-                break;
-            }
-            i -= 1;
-            let element = &mut self.elements[i];
-            if element.end >= range.end {
-                element.end = ((element.end as isize) + delta_bytes) as usize;
-            } else if element.end > range.start {
-                element.end = range.start;
-            }
-            if element.start >= range.end {
-                element.start =
-                    ((element.start as isize) + delta_bytes) as usize;
-            } else if element.start > range.start {
-                element.start = range.start;
-            }
-            if element.end <= element.start {
-                if i == elements_len - 1 {
-                    // preserve the last element, even if empty
-                    element.end = element.start;
-                } else {
-                    self.elements.remove(i);
-                    elements_len -= 1;
-                    continue;
-                }
-            }
-            if element.start >= range.start {
-                break; // all subsequent elements will be before the edit
-            }
-        }
+        self.wrap_to_width_with(width, &WrapOptions::default())
     }
 }
 
@@ -578,26 +618,27 @@ impl LineChar {
     /// background, therefore we can't count on them looking the same just
     /// because the color indices are the same.)
     pub fn endfills_same_as(&self, other: &LineChar) -> bool {
-        let a_underline = self.style.contains(Style::UNDERLINE);
-        let b_underline = other.style.contains(Style::UNDERLINE);
-        if a_underline != b_underline {
+        let a_lines = self.style & Style::LINES;
+        let b_lines = other.style & Style::LINES;
+        if a_lines != b_lines {
             return false;
         }
-        debug_assert_eq!(a_underline, b_underline);
+        // (any lines are drawn in the foreground color)
+        let has_lines = !a_lines.is_empty();
         let a_inverse = self.style.contains(Style::INVERSE);
         let b_inverse = other.style.contains(Style::INVERSE);
         if a_inverse != b_inverse {
             false
         } else if a_inverse {
             debug_assert!(b_inverse);
-            if a_underline && self.bg != other.bg {
+            if has_lines && self.bg != other.bg {
                 return false;
             }
             self.fg == other.fg
         } else {
             debug_assert!(!a_inverse);
             debug_assert!(!b_inverse);
-            if a_underline && self.fg != other.fg {
+            if has_lines && self.fg != other.fg {
                 return false;
             }
             self.bg == other.bg
@@ -613,6 +654,13 @@ impl<'a> LineCharIterator<'a> {
             indices: line.text.char_indices(),
         }
     }
+    /// The link, if any, on the `char` most recently returned by `next`.
+    pub(crate) fn cur_link(&self) -> Option<&'a str> {
+        self.line
+            .elements
+            .get(self.cur_element)
+            .and_then(|x| x.link.as_deref())
+    }
 }
 
 impl Iterator for LineCharIterator<'_> {
@@ -4,8 +4,15 @@ impl Line {
     /// Adds additional text to the `Line`, respecting a subset of ANSI escape
     /// sequences in the process.
     ///
-    /// We support only CSI SGR codes (escape followed by `[` ending with `m`).
-    /// All unsupported codes are passed through unchanged.
+    /// We support only CSI SGR codes (escape followed by `[` ending with `m`)
+    /// and OSC 8 hyperlinks (which become [links][3]). All unsupported codes
+    /// are passed through unchanged. SGR codes include the bright, 256-color,
+    /// and 24-bit color codes, which become [`Color::Indexed`][1] and
+    /// [`Color::Rgb`][2] as appropriate.
+    ///
+    /// [1]: enum.Color.html#variant.Indexed
+    /// [2]: enum.Color.html#variant.Rgb
+    /// [3]: #method.set_link
     pub fn add_ansi_text<'a, T>(&mut self, input_line: T) -> &mut Line
     where
         T: Into<Cow<'a, str>>,
@@ -25,6 +32,35 @@ impl Line {
                 let Some((_, ch)) = char_indices.next() else {
                     break;
                 };
+                if ch == ']' {
+                    let osc_start = unescaped_start + sequence_start + 2;
+                    let Some((body, osc_len)) =
+                        split_osc(&input_line[osc_start..])
+                    else {
+                        continue;
+                    };
+                    // OSC 8 ; params ; URI
+                    let Some((_params, uri)) = body
+                        .strip_prefix("8;")
+                        .and_then(|x| x.split_once(';'))
+                    else {
+                        continue;
+                    };
+                    if sequence_start != 0 {
+                        self.add_text(
+                            &input_line[unescaped_start
+                                ..unescaped_start + sequence_start],
+                        );
+                    }
+                    // an empty URI ends the link
+                    self.set_link(if uri.is_empty() {
+                        None
+                    } else {
+                        Some(uri)
+                    });
+                    unescaped_start = osc_start + osc_len;
+                    continue 'outer;
+                }
                 if ch != '[' {
                     continue;
                 }
@@ -73,15 +109,31 @@ impl Line {
                         1 => drop(self.activate_style(Style::BOLD)),
                         2 => drop(self.activate_style(Style::DIM)),
                         3 => drop(self.activate_style(Style::ITALIC)),
-                        4 => drop(self.activate_style(Style::UNDERLINE)),
+                        4 => drop(self.set_style(
+                            (self.get_style() - Style::DOUBLE_UNDERLINE)
+                                | Style::UNDERLINE,
+                        )),
+                        5 => drop(self.activate_style(Style::BLINK)),
                         7 => drop(self.activate_style(Style::REVERSE)),
-                        21 => drop(self.deactivate_style(Style::BOLD)),
+                        8 => drop(self.activate_style(Style::CONCEAL)),
+                        9 => drop(self.activate_style(Style::STRIKETHROUGH)),
+                        21 => drop(self.set_style(
+                            (self.get_style() - Style::UNDERLINE)
+                                | Style::DOUBLE_UNDERLINE,
+                        )),
                         22 => drop(
                             self.deactivate_style(Style::BOLD | Style::DIM),
                         ),
                         23 => drop(self.deactivate_style(Style::ITALIC)),
-                        24 => drop(self.deactivate_style(Style::UNDERLINE)),
+                        24 => drop(self.deactivate_style(
+                            Style::UNDERLINE | Style::DOUBLE_UNDERLINE,
+                        )),
+                        25 => drop(self.deactivate_style(Style::BLINK)),
                         27 => drop(self.deactivate_style(Style::REVERSE)),
+                        28 => drop(self.deactivate_style(Style::CONCEAL)),
+                        29 => {
+                            self.deactivate_style(Style::STRIKETHROUGH);
+                        }
                         30 => drop(self.set_fg_color(Some(Color::Black))),
                         31 => drop(self.set_fg_color(Some(Color::Red))),
                         32 => drop(self.set_fg_color(Some(Color::Green))),
@@ -100,18 +152,48 @@ impl Line {
                         46 => drop(self.set_bg_color(Some(Color::Cyan))),
                         47 => drop(self.set_bg_color(Some(Color::White))),
                         49 => drop(self.set_bg_color(None)),
+                        53 => drop(self.activate_style(Style::OVERLINE)),
+                        55 => drop(self.deactivate_style(Style::OVERLINE)),
+                        90..=97 => drop(self.set_fg_color(Some(
+                            Color::Indexed((code - 90 + 8) as u8),
+                        ))),
+                        100..=107 => drop(self.set_bg_color(Some(
+                            Color::Indexed((code - 100 + 8) as u8),
+                        ))),
                         38 | 48 | 58 => {
-                            match codes.next() {
-                                Some(5) => {
-                                    // 8-bit color, not supported
-                                    let _index = codes.next();
-                                }
+                            let color = match codes.next() {
+                                Some(5) => codes
+                                    .next()
+                                    .and_then(|x| u8::try_from(x).ok())
+                                    .map(Color::Indexed),
                                 Some(2) => {
-                                    // RGB color, not supported
-                                    let _r = codes.next();
-                                    let _g = codes.next();
-                                    let _b = codes.next();
+                                    let mut component = || {
+                                        codes
+                                            .next()
+                                            .and_then(|x| u8::try_from(x).ok())
+                                    };
+                                    match (
+                                        component(),
+                                        component(),
+                                        component(),
+                                    ) {
+                                        (Some(r), Some(g), Some(b)) => {
+                                            Some(Color::Rgb(r, g, b))
+                                        }
+                                        _ => None,
+                                    }
+                                }
+                                _ => None,
+                            };
+                            match (code, color) {
+                                (38, Some(color)) => {
+                                    self.set_fg_color(Some(color));
                                 }
+                                (48, Some(color)) => {
+                                    self.set_bg_color(Some(color));
+                                }
+                                // underline color, or an invalid color, is
+                                // not supported
                                 _ => (),
                             }
                         }
@@ -130,20 +212,102 @@ impl Line {
     }
 }
 
+/// Given the text following an OSC introducer (escape followed by `]`),
+/// returns the body of the OSC sequence and the number of bytes it occupies
+/// including its terminator (either ST or BEL). Returns `None` if the sequence
+/// is unterminated.
+fn split_osc(input: &str) -> Option<(&str, usize)> {
+    let bytes = input.as_bytes();
+    for (i, &byte) in bytes.iter().enumerate() {
+        match byte {
+            0x07 => return Some((&input[..i], i + 1)),
+            0x1B if bytes.get(i + 1) == Some(&b'\\') => {
+                return Some((&input[..i], i + 2))
+            }
+            // any other control character means this isn't a well-formed
+            // sequence
+            0x00..=0x1F | 0x7F => return None,
+            _ => (),
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     #[test]
     fn basic_ansi_test() {
         let mut line = Line::new();
-        line.add_ansi_text("Hello \x1B[1;32mWorld\x1B[21m!\x1B[0m Yay!");
+        line.add_ansi_text("Hello \x1B[1;32mWorld\x1B[22m!\x1B[0m Yay!");
         assert_eq!(
             line,
             liso!("Hello ", +bold, fg=green, "World", -bold, "!", reset, " Yay!")
         );
         assert_eq!(
-            liso!(ansi "Hello \x1B[1;32mWorld\x1B[21m!\x1B[0m Yay!"),
+            liso!(ansi "Hello \x1B[1;32mWorld\x1B[22m!\x1B[0m Yay!"),
             liso!("Hello ", +bold, fg=green, "World", -bold, "!", reset, " Yay!")
         );
     }
+    #[test]
+    fn extended_color_ansi_test() {
+        assert_eq!(
+            liso!(ansi "\x1B[38;5;202mOrange\x1B[48;2;1;2;3m on dark\x1B[91;49m!"),
+            liso!(
+                fg = Some(Color::Indexed(202)),
+                "Orange",
+                bg = rgb(1, 2, 3),
+                " on dark",
+                fg = indexed(9),
+                bg = none,
+                "!"
+            )
+        );
+        // malformed extended colors are ignored, as are underline colors
+        assert_eq!(
+            liso!(ansi "\x1B[38;5;999;58;5;1mHi\x1B[38;2;1;2m!"),
+            liso!("Hi!")
+        );
+    }
+    #[test]
+    fn osc8_link_ansi_test() {
+        assert_eq!(
+            liso!(ansi "See \x1B]8;;https://example.com/\x1B\\the \x1B[1mdocs\x1B]8;;\x07\x1B[0m."),
+            liso!(
+                "See ",
+                link = "https://example.com/",
+                "the ",
+                bold,
+                "docs",
+                link = none,
+                plain,
+                "."
+            )
+        );
+        // other OSC sequences, and unterminated ones, are passed through
+        assert_eq!(
+            liso!(ansi "\x1B]0;title\x07\x1B]8;;nope").as_str(),
+            "^[]0;title^G^[]8;;nope"
+        );
+    }
+    #[test]
+    fn extended_style_ansi_test() {
+        assert_eq!(
+            liso!(ansi "\x1B[9mold\x1B[29m \x1B[5;53mhey\x1B[25;55;21m!\x1B[24;8mshh\x1B[28m."),
+            liso!(
+                +strikethrough,
+                "old",
+                -strikethrough,
+                " ",
+                +blink +overline,
+                "hey",
+                -blink -overline +double_underline,
+                "!",
+                -double_underline +conceal,
+                "shh",
+                -conceal,
+                "."
+            )
+        );
+    }
 }
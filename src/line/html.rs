@@ -0,0 +1,242 @@
+use super::*;
+
+impl Line {
+    /// Renders the `Line` as a fragment of HTML. Each styled span becomes a
+    /// `<span>` with an inline `style` attribute, and each link becomes an
+    /// `<a>`. Text is escaped; newlines are left as they are, so you will want
+    /// to put the result inside a `<pre>` (or something styled with
+    /// `white-space: pre`).
+    ///
+    /// Only links to `http:`, `https:`, `mailto:`, and `file:` URLs become
+    /// `<a>`s. Text linking anywhere else (e.g. a `javascript:` URL) is
+    /// rendered without its link, so that it's safe to show output you didn't
+    /// write.
+    ///
+    /// Colors become the xterm palette's RGB values. Spans with inverse video
+    /// and a default color use the CSS variables `--liso-fg` and `--liso-bg`,
+    /// falling back to black and white. [`html_transcript`][1] sets these for
+    /// you.
+    ///
+    /// ```rust
+    /// # use liso::liso;
+    /// let line = liso!("1 < 2 ", bold, fg = red, "& true");
+    /// assert_eq!(line.to_html(), "1 &lt; 2 <span style=\"font-weight:bold;\
+    ///            color:#cd0000\">&amp; true</span>");
+    /// ```
+    ///
+    /// [1]: fn.html_transcript.html
+    pub fn to_html(&self) -> String {
+        let mut ret = String::with_capacity(self.text.len());
+        let mut cur_link = None;
+        for element in self.elements.iter() {
+            let text = &self.text[element.start..element.end];
+            if text.is_empty() {
+                continue;
+            }
+            let link = element.link.as_deref().filter(|x| is_safe_href(x));
+            if link != cur_link {
+                if cur_link.is_some() {
+                    ret += "</a>";
+                }
+                if let Some(link) = link {
+                    ret += "<a href=\"";
+                    escape_html_into(&mut ret, link);
+                    ret += "\">";
+                }
+                cur_link = link;
+            }
+            let css = element_css(element);
+            if css.is_empty() {
+                escape_html_into(&mut ret, text);
+            } else {
+                ret += "<span style=\"";
+                ret += &css;
+                ret += "\">";
+                escape_html_into(&mut ret, text);
+                ret += "</span>";
+            }
+        }
+        if cur_link.is_some() {
+            ret += "</a>";
+        }
+        ret
+    }
+}
+
+/// Renders a sequence of [`Line`](struct.Line.html)s as a standalone HTML
+/// document, with each `Line` on its own line, using
+/// [`Line::to_html`](struct.Line.html#method.to_html). Good for pasting
+/// session output into a bug report or serving it from a dashboard without
+/// losing its styling.
+///
+/// `title` becomes the document's title. The page is light text on a dark
+/// background; change the `--liso-fg` and `--liso-bg` variables in the
+/// stylesheet if you'd rather have something else.
+///
+/// ```rust
+/// # use liso::{liso, html_transcript};
+/// let lines = [liso!("$ make"), liso!(fg = red, "error: no rule")];
+/// let html = html_transcript(&lines, "Build log");
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// assert!(html.contains("<title>Build log</title>"));
+/// assert!(html.contains("$ make\n<span style=\"color:#cd0000\">"));
+/// ```
+pub fn html_transcript<'a, I>(lines: I, title: &str) -> String
+where
+    I: IntoIterator<Item = &'a Line>,
+{
+    let mut ret = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>",
+    );
+    escape_html_into(&mut ret, title);
+    ret += "</title>\n<style>\n\
+            :root { --liso-fg: #e5e5e5; --liso-bg: #000000; }\n\
+            body { margin: 0; background-color: var(--liso-bg); }\n\
+            pre.liso { margin: 0; padding: 1em; color: var(--liso-fg); \
+            background-color: var(--liso-bg); \
+            font-family: monospace; white-space: pre-wrap; }\n\
+            pre.liso a { color: inherit; }\n\
+            </style>\n</head>\n<body>\n<pre class=\"liso\">";
+    for (n, line) in lines.into_iter().enumerate() {
+        if n != 0 {
+            ret.push('\n');
+        }
+        ret += &line.to_html();
+    }
+    ret += "</pre>\n</body>\n</html>\n";
+    ret
+}
+
+/// The inline CSS for a given element, or an empty string if it's unstyled.
+fn element_css(element: &LineElement) -> String {
+    let style = normalize_style(element.style);
+    let mut props: Vec<Cow<str>> = Vec::new();
+    if style.contains(Style::BOLD) {
+        props.push("font-weight:bold".into());
+    }
+    if style.contains(Style::DIM) {
+        props.push("opacity:0.6".into());
+    }
+    if style.contains(Style::ITALIC) {
+        props.push("font-style:italic".into());
+    }
+    let mut decorations = vec![];
+    if style.intersects(Style::UNDERLINE | Style::DOUBLE_UNDERLINE) {
+        decorations.push("underline");
+    }
+    if style.contains(Style::OVERLINE) {
+        decorations.push("overline");
+    }
+    if style.contains(Style::STRIKETHROUGH) {
+        decorations.push("line-through");
+    }
+    if !decorations.is_empty() {
+        props.push(
+            format!("text-decoration-line:{}", decorations.join(" ")).into(),
+        );
+    }
+    if style.contains(Style::DOUBLE_UNDERLINE) {
+        props.push("text-decoration-style:double".into());
+    }
+    // (blink is deliberately left out)
+    let fg = element.fg.map(css_color);
+    let bg = element.bg.map(css_color);
+    let (fg, bg) = if style.contains(Style::INVERSE) {
+        (
+            Some(bg.unwrap_or_else(|| "var(--liso-bg,#fff)".to_string())),
+            Some(fg.unwrap_or_else(|| "var(--liso-fg,#000)".to_string())),
+        )
+    } else {
+        (fg, bg)
+    };
+    if style.contains(Style::CONCEAL) {
+        // still there, still selectable, just not visible
+        props.push("color:transparent".into());
+    } else if let Some(fg) = fg {
+        props.push(format!("color:{fg}").into());
+    }
+    if let Some(bg) = bg {
+        props.push(format!("background-color:{bg}").into());
+    }
+    props.join(";")
+}
+
+/// Whether a link is to one of the URL schemes we're willing to put in an
+/// `href`.
+fn is_safe_href(link: &str) -> bool {
+    const SAFE_SCHEMES: &[&str] = &["http", "https", "mailto", "file"];
+    match link.split_once(':') {
+        Some((scheme, _)) => SAFE_SCHEMES
+            .iter()
+            .any(|safe| safe.eq_ignore_ascii_case(scheme)),
+        None => false,
+    }
+}
+
+fn css_color(color: Color) -> String {
+    let (r, g, b) = color.as_rgb();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape_html_into(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => *out += "&amp;",
+            '<' => *out += "&lt;",
+            '>' => *out += "&gt;",
+            '"' => *out += "&quot;",
+            '\'' => *out += "&#39;",
+            _ => out.push(ch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn html_rendering() {
+        let line = liso!(
+            "<plain> ",
+            inverse,
+            "inv",
+            plain,
+            link = "https://example.com/?a=1&b=\"2\"",
+            underline + strikethrough,
+            bg = indexed(21),
+            "link",
+            link = none,
+            reset,
+            " ",
+            conceal,
+            fg = rgb(1, 2, 3),
+            "shh"
+        );
+        assert_eq!(
+            line.to_html(),
+            "&lt;plain&gt; \
+             <span style=\"color:var(--liso-bg,#fff);\
+             background-color:var(--liso-fg,#000)\">inv</span>\
+             <a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\">\
+             <span style=\"text-decoration-line:underline line-through;\
+             background-color:#0000ff\">link</span></a> \
+             <span style=\"color:transparent\">shh</span>"
+        );
+        assert_eq!(Line::new().to_html(), "");
+    }
+    #[test]
+    fn unsafe_links_dropped() {
+        let line = liso!(
+            link = "javascript:alert(1)",
+            "click",
+            link = "MAILTO:liso@example.com",
+            " me"
+        );
+        assert_eq!(
+            line.to_html(),
+            "click<a href=\"MAILTO:liso@example.com\"> me</a>"
+        );
+        let line = liso!(link = " javascript:alert(1)", "or me");
+        assert_eq!(line.to_html(), "or me");
+    }
+}
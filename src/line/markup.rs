@@ -0,0 +1,401 @@
+use super::*;
+
+use std::fmt;
+
+impl Line {
+    /// Parses a line of text containing a small, HTML-like markup language,
+    /// for styling text that comes from somewhere other than your code:
+    /// configuration files, translation catalogs, and the like. (If the text
+    /// *does* come from your code, the [`liso!`](macro.liso.html) macro is
+    /// more convenient.)
+    ///
+    /// Tags come in pairs, and must be properly nested. `</>` closes whatever
+    /// tag was opened most recently.
+    ///
+    /// - `<b>`/`<bold>`, `<dim>`, `<i>`/`<italic>`, `<u>`/`<underline>`,
+    ///   `<uu>`/`<double_underline>`, `<inverse>`/`<reverse>`,
+    ///   `<s>`/`<strikethrough>`, `<o>`/`<overline>`, `<blink>`, `<conceal>`
+    ///   Turn on the given [`Style`][1].
+    /// - `<fg=color>`, `<bg=color>`
+    ///   Change the foreground or background color. The color may be anything
+    ///   that [`Color` can parse][2], or `none` for the default color.
+    /// - `<link=url>`
+    ///   Make the text a link to the given URL.
+    /// - `<theme=name>`
+    ///   Style the text using the named entry from the current
+    ///   [`Theme`][4].
+    ///
+    /// Write `&lt;`, `&gt;`, and `&amp;` to get a literal `<`, `>`, or `&`.
+    /// [`escape_markup`][3] will do this for you, which you *must* do if you
+    /// are substituting arbitrary text into markup.
+    ///
+    /// ```rust
+    /// # use liso::{liso, Line, Color};
+    /// let line = Line::from_markup("<b>HP:</b> <fg=red>3 &lt; 10</fg>")
+    ///     .unwrap();
+    /// assert_eq!(line, liso!(bold, "HP:", plain, " ", fg = red, "3 < 10",
+    ///                        fg = none));
+    /// assert!(Line::from_markup("<b>oops</i>").is_err());
+    /// ```
+    ///
+    /// [1]: struct.Style.html
+    /// [2]: enum.Color.html#impl-FromStr-for-Color
+    /// [3]: fn.escape_markup.html
+    /// [4]: struct.Theme.html
+    pub fn from_markup(markup: &str) -> Result<Line, MarkupError> {
+        let mut ret = Line::new();
+        ret.add_markup(markup)?;
+        Ok(ret)
+    }
+    /// Adds text written in [markup](#method.from_markup) to the `Line`.
+    /// Styles, colors, and links are restored to what they were before the
+    /// markup once it ends. If there is an error, the `Line` is left
+    /// unchanged.
+    pub fn add_markup(
+        &mut self,
+        markup: &str,
+    ) -> Result<&mut Line, MarkupError> {
+        let parsed = parse_markup(markup, self)?;
+        self.append_line(&parsed);
+        Ok(self)
+    }
+}
+
+/// Escapes a string so that it will appear literally when used as part of
+/// [markup](struct.Line.html#method.from_markup).
+///
+/// ```rust
+/// # use liso::{escape_markup, Line};
+/// let name = "<script>";
+/// let markup = format!("Hello, <b>{}</b>!", escape_markup(name));
+/// assert_eq!(Line::from_markup(&markup).unwrap().as_str(),
+///            "Hello, <script>!");
+/// ```
+pub fn escape_markup(text: &str) -> Cow<'_, str> {
+    if !text.contains(['<', '>', '&']) {
+        return Cow::Borrowed(text);
+    }
+    let mut ret = String::with_capacity(text.len() + 8);
+    for ch in text.chars() {
+        match ch {
+            '<' => ret += "&lt;",
+            '>' => ret += "&gt;",
+            '&' => ret += "&amp;",
+            _ => ret.push(ch),
+        }
+    }
+    Cow::Owned(ret)
+}
+
+/// An error in [markup](struct.Line.html#method.from_markup).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkupError {
+    /// The byte offset within the markup at which the problem was found.
+    pub position: usize,
+    /// What the problem was.
+    pub kind: MarkupErrorKind,
+}
+
+/// What kind of [`MarkupError`](struct.MarkupError.html) occurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MarkupErrorKind {
+    /// A tag we don't know, such as `<blorp>`.
+    UnknownTag(String),
+    /// A tag that needs a value didn't get one, such as `<fg>`, or one that
+    /// doesn't take a value got one, such as `<b=1>`.
+    BadValue(String),
+    /// A color we couldn't parse, such as `<fg=chartreuse>`.
+    BadColor(String),
+    /// A closing tag didn't match the most recently opened tag.
+    MismatchedClose {
+        /// The tag that was open (if any).
+        expected: Option<String>,
+        /// The closing tag we found instead.
+        found: String,
+    },
+    /// The markup ended while this tag was still open.
+    UnclosedTag(String),
+    /// A `<` without a matching `>`.
+    UnterminatedTag,
+    /// An entity we don't know, such as `&nbsp;`, or a stray `&`.
+    BadEntity(String),
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            MarkupErrorKind::UnknownTag(x) => write!(f, "unknown tag <{x}>"),
+            MarkupErrorKind::BadValue(x) => {
+                write!(f, "bad value in tag <{x}>")
+            }
+            MarkupErrorKind::BadColor(x) => write!(f, "unknown color {x:?}"),
+            MarkupErrorKind::MismatchedClose {
+                expected: Some(expected),
+                found,
+            } => write!(f, "expected </{expected}>, found </{found}>"),
+            MarkupErrorKind::MismatchedClose {
+                expected: None,
+                found,
+            } => write!(f, "</{found}> closes a tag that was never opened"),
+            MarkupErrorKind::UnclosedTag(x) => write!(f, "<{x}> never closed"),
+            MarkupErrorKind::UnterminatedTag => write!(f, "unterminated tag"),
+            MarkupErrorKind::BadEntity(x) => write!(f, "unknown entity {x:?}"),
+        }?;
+        write!(f, " at byte {}", self.position)
+    }
+}
+
+impl std::error::Error for MarkupError {}
+
+fn style_for_tag(name: &str) -> Option<Style> {
+    Some(match name {
+        "b" | "bold" => Style::BOLD,
+        "dim" => Style::DIM,
+        "i" | "italic" => Style::ITALIC,
+        "u" | "underline" => Style::UNDERLINE,
+        "uu" | "double_underline" => Style::DOUBLE_UNDERLINE,
+        "inverse" | "reverse" => Style::INVERSE,
+        "s" | "strikethrough" => Style::STRIKETHROUGH,
+        "o" | "overline" => Style::OVERLINE,
+        "blink" => Style::BLINK,
+        "conceal" => Style::CONCEAL,
+        _ => return None,
+    })
+}
+
+fn parse_color(value: &str) -> Result<Option<Color>, ()> {
+    if value == "none" {
+        Ok(None)
+    } else {
+        value.parse().map(Some).map_err(|_| ())
+    }
+}
+
+/// Replaces the entities in `text`, which begins at byte `offset` of the
+/// markup.
+fn unescape(text: &str, offset: usize) -> Result<Cow<'_, str>, MarkupError> {
+    if !text.contains('&') {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut ret = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        ret += &rest[..amp];
+        let entity = match rest[amp..].find(';') {
+            Some(semi) => &rest[amp..amp + semi + 1],
+            None => &rest[amp..amp + 1],
+        };
+        ret.push(match entity {
+            "&lt;" => '<',
+            "&gt;" => '>',
+            "&amp;" => '&',
+            _ => {
+                return Err(MarkupError {
+                    position: offset + (text.len() - rest.len()) + amp,
+                    kind: MarkupErrorKind::BadEntity(entity.to_string()),
+                })
+            }
+        });
+        rest = &rest[amp + entity.len()..];
+    }
+    ret += rest;
+    Ok(Cow::Owned(ret))
+}
+
+/// The byte offset at which `part`, which must be a slice of `whole`, begins.
+fn offset_in(whole: &str, part: &str) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+/// Parses markup into a new `Line`, starting out with the attributes
+/// currently in effect on `base`.
+fn parse_markup(markup: &str, base: &Line) -> Result<Line, MarkupError> {
+    let mut line = Line::new();
+    line.restore_attrs(&base.current_attrs());
+    // each open tag, along with where it started and the attributes that were
+    // in effect before it
+    let mut stack: Vec<(&str, usize, LineElement)> = vec![];
+    let mut pos = 0;
+    while pos < markup.len() {
+        let Some(lt) = markup[pos..].find('<') else {
+            line.add_text(unescape(&markup[pos..], pos)?);
+            break;
+        };
+        if lt != 0 {
+            line.add_text(unescape(&markup[pos..pos + lt], pos)?);
+        }
+        let tag_start = pos + lt;
+        let Some(gt) = markup[tag_start..].find('>') else {
+            return Err(MarkupError {
+                position: tag_start,
+                kind: MarkupErrorKind::UnterminatedTag,
+            });
+        };
+        let tag = markup[tag_start + 1..tag_start + gt].trim();
+        pos = tag_start + gt + 1;
+        if let Some(closing) = tag.strip_prefix('/') {
+            let closing = closing.trim();
+            match stack.pop() {
+                Some((name, _, attrs))
+                    if closing.is_empty() || closing == name =>
+                {
+                    line.restore_attrs(&attrs);
+                }
+                other => {
+                    return Err(MarkupError {
+                        position: tag_start,
+                        kind: MarkupErrorKind::MismatchedClose {
+                            expected: other.map(|x| x.0.to_string()),
+                            found: closing.to_string(),
+                        },
+                    })
+                }
+            }
+            continue;
+        }
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (tag, None),
+        };
+        let error = |kind| MarkupError {
+            position: tag_start,
+            kind,
+        };
+        stack.push((name, tag_start, line.current_attrs()));
+        match (name, value) {
+            ("fg", Some(value)) => {
+                let color = parse_color(value).map_err(|_| {
+                    error(MarkupErrorKind::BadColor(value.to_string()))
+                })?;
+                line.set_fg_color(color);
+            }
+            ("bg", Some(value)) => {
+                let color = parse_color(value).map_err(|_| {
+                    error(MarkupErrorKind::BadColor(value.to_string()))
+                })?;
+                line.set_bg_color(color);
+            }
+            ("link", Some(value)) => {
+                let value_start = offset_in(markup, value);
+                line.set_link(Some(&unescape(value, value_start)?));
+            }
+            ("theme", Some(value)) => {
+                let value_start = offset_in(markup, value);
+                line.set_theme(Some(&unescape(value, value_start)?));
+            }
+            ("fg" | "bg" | "link" | "theme", None) => {
+                return Err(error(MarkupErrorKind::BadValue(tag.to_string())))
+            }
+            (name, value) => match style_for_tag(name) {
+                Some(_) if value.is_some() => {
+                    return Err(error(MarkupErrorKind::BadValue(
+                        tag.to_string(),
+                    )))
+                }
+                Some(style) => drop(line.activate_style(style)),
+                None => {
+                    return Err(error(MarkupErrorKind::UnknownTag(
+                        name.to_string(),
+                    )))
+                }
+            },
+        }
+    }
+    if let Some((name, position, ..)) = stack.pop() {
+        return Err(MarkupError {
+            position,
+            kind: MarkupErrorKind::UnclosedTag(name.to_string()),
+        });
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn markup_parsing() {
+        assert_eq!(
+            Line::from_markup(
+                "<fg=#102030>a<b>b<bg=208>c</>d</b><link=x?a=1&amp;b=2>e\
+                 <u>f</u></link><fg=none>g</fg>&gt;</fg><theme=muted>h</theme>"
+            )
+            .unwrap(),
+            liso!(
+                fg = rgb(0x10, 0x20, 0x30),
+                "a",
+                bold,
+                "b",
+                bg = indexed(208),
+                "c",
+                bg = none,
+                "d",
+                plain,
+                link = "x?a=1&b=2",
+                "e",
+                underline,
+                "f",
+                plain,
+                link = none,
+                fg = none,
+                "g",
+                fg = rgb(0x10, 0x20, 0x30),
+                ">",
+                fg = none,
+                theme = "muted",
+                "h",
+                theme = none
+            )
+        );
+        // the markup's effects end with the markup
+        let mut line = liso!(fg = blue, "x");
+        line.add_markup("<fg=red>y</fg>z").unwrap();
+        assert_eq!(line, liso!(fg = blue, "x", fg = red, "y", fg = blue, "z"));
+    }
+    #[test]
+    fn markup_errors() {
+        let kind = |markup| Line::from_markup(markup).unwrap_err().kind;
+        let err = Line::from_markup("ab<b>cd</i>").unwrap_err();
+        assert_eq!(err.position, 7);
+        assert_eq!(
+            err.kind,
+            MarkupErrorKind::MismatchedClose {
+                expected: Some("b".to_string()),
+                found: "i".to_string()
+            }
+        );
+        assert_eq!(
+            kind("</b>"),
+            MarkupErrorKind::MismatchedClose {
+                expected: None,
+                found: "b".to_string()
+            }
+        );
+        assert_eq!(kind("<b>"), MarkupErrorKind::UnclosedTag("b".to_string()));
+        assert_eq!(kind("a <b"), MarkupErrorKind::UnterminatedTag);
+        assert_eq!(kind("<x>"), MarkupErrorKind::UnknownTag("x".to_string()));
+        assert_eq!(kind("<fg>"), MarkupErrorKind::BadValue("fg".to_string()));
+        assert_eq!(
+            kind("<b=1>"),
+            MarkupErrorKind::BadValue("b=1".to_string())
+        );
+        assert_eq!(
+            kind("<fg=puce>"),
+            MarkupErrorKind::BadColor("puce".to_string())
+        );
+        let err = Line::from_markup("a &nbsp; b").unwrap_err();
+        assert_eq!(err.position, 2);
+        assert_eq!(err.kind, MarkupErrorKind::BadEntity("&nbsp;".to_string()));
+        assert_eq!(kind("a & b"), MarkupErrorKind::BadEntity("&".to_string()));
+        let err = Line::from_markup("< link = a&b; >x</link>").unwrap_err();
+        assert_eq!(err.position, 10);
+        assert_eq!(err.kind, MarkupErrorKind::BadEntity("&b;".to_string()));
+        let err = Line::from_markup("<theme=  &c;\t>x</>").unwrap_err();
+        assert_eq!(err.position, 9);
+        assert_eq!(
+            format!("{}", Line::from_markup("<b>").unwrap_err()),
+            "<b> never closed at byte 0"
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use super::*;
+
+use std::ops::{Bound, RangeBounds};
+
+use unicode_width::UnicodeWidthChar;
+
+impl Line {
+    /// Returns a new `Line` containing only the given byte range of this one,
+    /// with all of its [`Style`][1], [`Color`][2], link, and theme information
+    /// intact.
+    ///
+    /// Panics if either end of the range is out of bounds or isn't on a
+    /// `char` boundary, the same as slicing a `str` would.
+    ///
+    /// ```rust
+    /// # use liso::liso;
+    /// let line = liso!("Hello ", bold, fg = green, "World", plain, "!");
+    /// assert_eq!(line.slice(3..8), liso!("lo ", bold, fg = green, "Wo"));
+    /// assert_eq!(line.slice(..), line);
+    /// ```
+    ///
+    /// [1]: struct.Style.html
+    /// [2]: enum.Color.html
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Line {
+        let start = match range.start_bound() {
+            Bound::Included(&x) => x,
+            Bound::Excluded(&x) => x + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&x) => x + 1,
+            Bound::Excluded(&x) => x,
+            Bound::Unbounded => self.text.len(),
+        };
+        // (let `str` do the bounds checking for us)
+        let _ = &self.text[start..end];
+        let mut ret = Line::new();
+        for element in self.elements.iter() {
+            let el_start = element.start.max(start);
+            let el_end = element.end.min(end);
+            // keep elements with text in range, and also empty ones that are
+            // in range (so that a trailing change of attributes survives)
+            if el_start < el_end
+                || (element.start == element.end
+                    && (start..=end).contains(&element.start))
+            {
+                ret.restore_attrs(element);
+                ret.append_text(Cow::Borrowed(&self.text[el_start..el_end]));
+            }
+        }
+        ret
+    }
+    /// Splits the `Line` at each newline, the way [`str::lines`][1] does,
+    /// returning each line as a separate `Line` with its styling intact. The
+    /// newlines themselves are not included.
+    ///
+    /// ```rust
+    /// # use liso::liso;
+    /// let line = liso!("one\n", fg = red, "two\nthree\n");
+    /// let lines: Vec<_> = line.lines().collect();
+    /// assert_eq!(lines, vec![liso!("one"), liso!(fg = red, "two"),
+    ///                        liso!(fg = red, "three")]);
+    /// ```
+    ///
+    /// [1]: https://doc.rust-lang.org/std/primitive.str.html#method.lines
+    pub fn lines(&self) -> impl Iterator<Item = Line> + '_ {
+        let mut start = 0;
+        self.text.split_inclusive('\n').map(move |piece| {
+            let piece_start = start;
+            start += piece.len();
+            self.slice(
+                piece_start..piece_start + piece.trim_end_matches('\n').len(),
+            )
+        })
+    }
+    /// Returns the number of terminal columns needed to display the widest
+    /// line within this `Line`.
+    ///
+    /// ```rust
+    /// # use liso::liso;
+    /// assert_eq!(liso!("abc\n", bold, "日本語").display_width(), 6);
+    /// ```
+    pub fn display_width(&self) -> usize {
+        self.text.split('\n').map(str_width).max().unwrap_or(0)
+    }
+    /// Shortens every line within this `Line` that is wider than the given
+    /// number of columns, replacing the end of it with `ellipsis`. The
+    /// ellipsis gets the same styling as the first character it replaces.
+    /// Lines that already fit are left alone. (If `ellipsis` is itself too
+    /// wide, it gets shortened too.)
+    ///
+    /// Where [`wrap_to_width`][1] keeps all of the text by adding lines, this
+    /// keeps the number of lines the same by dropping text. Good for status
+    /// lines that must fit within the terminal.
+    ///
+    /// ```rust
+    /// # use liso::liso;
+    /// let mut line = liso!("Status: ", fg = green, "all systems nominal");
+    /// line.truncate_to_width(14, "…");
+    /// assert_eq!(line, liso!("Status: ", fg = green, "all s…"));
+    /// ```
+    ///
+    /// [1]: #method.wrap_to_width
+    pub fn truncate_to_width(&mut self, width: usize, ellipsis: &str) {
+        let ellipsis = &ellipsis[..prefix_within_width(ellipsis, width)];
+        let room = width - str_width(ellipsis);
+        // (byte range to replace with the ellipsis, for each line too wide)
+        let mut cuts = vec![];
+        let mut start = 0;
+        for piece in self.text.split('\n') {
+            if str_width(piece) > width {
+                let cut = start + prefix_within_width(piece, room);
+                cuts.push(cut..start + piece.len());
+            }
+            start += piece.len() + 1;
+        }
+        if cuts.is_empty() {
+            return;
+        }
+        let mut ret = Line::new();
+        let mut pos = 0;
+        for cut in cuts.into_iter() {
+            ret.append_line(&self.slice(pos..cut.start));
+            ret.restore_attrs(&self.attrs_at(cut.start));
+            ret.add_text(ellipsis);
+            pos = cut.end;
+        }
+        ret.append_line(&self.slice(pos..));
+        *self = ret;
+    }
+    /// Splits the `Line` into the rows it would take up on a terminal of the
+    /// given width: one or more for each line within it (including an empty
+    /// one after a trailing newline), breaking between characters wherever a
+    /// line is too wide.
+    pub(crate) fn split_rows(&self, width: usize) -> Vec<Line> {
+        let mut ret = vec![];
+        let mut start = 0;
+        for piece in self.text.split('\n') {
+            let end = start + piece.len();
+            let mut row_start = start;
+            loop {
+                let rest = &self.text[row_start..end];
+                let mut len = prefix_within_width(rest, width);
+                if len == 0 && !rest.is_empty() {
+                    // (a character wider than the terminal gets a row to
+                    // itself)
+                    len = rest.chars().next().unwrap().len_utf8();
+                }
+                ret.push(self.slice(row_start..row_start + len));
+                row_start += len;
+                if row_start >= end {
+                    break;
+                }
+            }
+            start = end + 1;
+        }
+        ret
+    }
+}
+
+/// How many columns the given text takes up. Control characters count as
+/// zero, the same as when the text is being displayed.
+fn str_width(text: &str) -> usize {
+    text.chars()
+        .map(|ch| UnicodeWidthChar::width(ch).unwrap_or(0))
+        .sum()
+}
+
+/// How many bytes of the given text will fit within the given number of
+/// columns. Zero-width characters directly after the last character that fits
+/// are included.
+fn prefix_within_width(text: &str, width: usize) -> usize {
+    let mut used = 0;
+    for (index, ch) in text.char_indices() {
+        used += UnicodeWidthChar::width(ch).unwrap_or(0);
+        if used > width {
+            return index;
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn slicing_and_truncation() {
+        let url = "https://example.com/";
+        let line = liso!(
+            "ab",
+            bold,
+            "cd\nef",
+            link = url,
+            "gh\n",
+            link = none,
+            reset,
+            "日本語"
+        );
+        assert_eq!(line.slice(1..3), liso!("b", bold, "c"));
+        assert_eq!(line.slice(2..=2), liso!(bold, "c"));
+        assert_eq!(
+            line.slice(6..),
+            liso!(bold, "f", link = url, "gh\n", link = none, reset, "日本語")
+        );
+        assert_eq!(line.slice(0..0), Line::new());
+        let lines: Vec<Line> = line.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                liso!("ab", bold, "cd"),
+                liso!(bold, "ef", link = url, "gh"),
+                liso!("日本語"),
+            ]
+        );
+        assert_eq!(Line::new().lines().count(), 0);
+        assert_eq!(line.display_width(), 6);
+        let mut truncated = line.clone();
+        truncated.truncate_to_width(3, "~");
+        assert_eq!(
+            truncated,
+            liso!(
+                "ab",
+                bold,
+                "~\nef",
+                link = url,
+                "~\n",
+                link = none,
+                reset,
+                "日~"
+            )
+        );
+        let mut truncated = line.clone();
+        truncated.truncate_to_width(2, "…");
+        assert_eq!(
+            truncated,
+            liso!(
+                "a…",
+                bold,
+                "\ne…",
+                link = url,
+                "\n",
+                link = none,
+                reset,
+                "…"
+            )
+        );
+        let mut truncated = liso!("abc", fg = red, "def");
+        truncated.truncate_to_width(4, "…");
+        assert_eq!(truncated, liso!("abc", fg = red, "…"));
+        let mut untouched = line.clone();
+        untouched.truncate_to_width(6, "…");
+        assert_eq!(untouched, line);
+        assert_eq!(
+            line.split_rows(3),
+            vec![
+                liso!("ab", bold, "c"),
+                liso!(bold, "d"),
+                liso!(bold, "ef", link = url, "g"),
+                liso!(bold, link = url, "h"),
+                liso!("日"),
+                liso!("本"),
+                liso!("語"),
+            ]
+        );
+        assert_eq!(Line::new().split_rows(3), vec![Line::new()]);
+        assert_eq!(liso!("a\n").split_rows(3), vec![liso!("a"), Line::new()]);
+    }
+}
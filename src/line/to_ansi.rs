@@ -0,0 +1,282 @@
+use super::*;
+
+use std::fmt;
+
+/// A complete set of SGR attributes: style, foreground, background.
+pub(crate) type Attrs = (Style, Option<Color>, Option<Color>);
+
+impl Line {
+    /// Renders the `Line` as a string containing ANSI escape sequences,
+    /// suitable for writing to a log file, handing to another crate, or
+    /// sending to a terminal yourself. This is the inverse of
+    /// [`add_ansi_text`][1].
+    ///
+    /// As few SGR codes as possible are output. Links are output as OSC 8
+    /// sequences. If any style, color, or link is still active at the end of
+    /// the line, the string ends by resetting it. Colors are output as-is;
+    /// see [`Color::downgrade`][2] if that's a problem.
+    ///
+    /// ```rust
+    /// # use liso::liso;
+    /// let line = liso!("Hello ", bold, fg = green, "World", plain, "!");
+    /// assert_eq!(line.to_ansi_string(),
+    ///            "Hello \x1B[1;32mWorld\x1B[22m!\x1B[0m");
+    /// ```
+    ///
+    /// [1]: #method.add_ansi_text
+    /// [2]: enum.Color.html#method.downgrade
+    pub fn to_ansi_string(&self) -> String {
+        let mut ret = String::with_capacity(self.text.len());
+        let mut cur_attrs: Attrs = (Style::PLAIN, None, None);
+        let mut cur_link = None;
+        for element in self.elements.iter() {
+            let text = &self.text[element.start..element.end];
+            if text.is_empty() {
+                continue;
+            }
+            let attrs =
+                (normalize_style(element.style), element.fg, element.bg);
+            ret += &sgr_transition(cur_attrs, attrs);
+            cur_attrs = attrs;
+            let link = sanitize_link(element.link.as_deref());
+            if link != cur_link {
+                ret += &link_sequence(link);
+                cur_link = link;
+            }
+            ret += text;
+        }
+        if cur_link.is_some() {
+            ret += &link_sequence(None);
+        }
+        ret += &sgr_transition(cur_attrs, (Style::PLAIN, None, None));
+        ret
+    }
+}
+
+/// `{}` displays only the text of the `Line`, the same as
+/// [`as_str`](#method.as_str). `{:#}` displays it with ANSI escape sequences,
+/// the same as [`to_ansi_string`](#method.to_ansi_string).
+///
+/// ```rust
+/// # use liso::liso;
+/// let line = liso!(fg = red, "Error!");
+/// assert_eq!(format!("{}", line), "Error!");
+/// assert_eq!(format!("{:#}", line), "\x1B[31mError!\x1B[0m");
+/// ```
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str(&self.to_ansi_string())
+        } else {
+            f.write_str(&self.text)
+        }
+    }
+}
+
+/// Resolves conflicting styles the way ANSI terminals will display them.
+pub(crate) fn normalize_style(style: Style) -> Style {
+    let style = if style.contains(Style::BOLD | Style::DIM) {
+        style - Style::DIM
+    } else {
+        style
+    };
+    // double underline replaces underline, they can't both be on
+    if style.contains(Style::UNDERLINE | Style::DOUBLE_UNDERLINE) {
+        style - Style::UNDERLINE
+    } else {
+        style
+    }
+}
+
+/// Returns the shortest SGR sequence that will change a terminal's attributes
+/// from `old` to `new`, or an empty string if they're the same. The styles
+/// should already have gone through `normalize_style`.
+pub(crate) fn sgr_transition(old: Attrs, new: Attrs) -> String {
+    let (old_style, old_fg, old_bg) = old;
+    let (style, fg, bg) = new;
+    let mut piecemeal_gubbins: Vec<Cow<str>> = Vec::with_capacity(12);
+    let styles_to_set = style & !old_style;
+    let styles_to_clear = old_style & !style;
+    if styles_to_set.contains(Style::BOLD) {
+        piecemeal_gubbins.push("1".into());
+    } else if styles_to_clear.contains(Style::BOLD) {
+        piecemeal_gubbins.push("22".into()); // NOT 21
+    }
+    if styles_to_set.contains(Style::DIM) {
+        piecemeal_gubbins.push("2".into());
+    } else if styles_to_clear.contains(Style::DIM)
+        && !style.contains(Style::BOLD)
+    {
+        piecemeal_gubbins.push("22".into());
+    }
+    if styles_to_set.contains(Style::UNDERLINE) {
+        if styles_to_clear.contains(Style::DOUBLE_UNDERLINE) {
+            // some terminals can have both on at once
+            piecemeal_gubbins.push("24".into());
+        }
+        piecemeal_gubbins.push("4".into());
+    } else if styles_to_set.contains(Style::DOUBLE_UNDERLINE) {
+        piecemeal_gubbins.push("21".into());
+    } else if styles_to_clear
+        .intersects(Style::UNDERLINE | Style::DOUBLE_UNDERLINE)
+    {
+        piecemeal_gubbins.push("24".into());
+    }
+    if styles_to_set.contains(Style::INVERSE) {
+        piecemeal_gubbins.push("7".into());
+    } else if styles_to_clear.contains(Style::INVERSE) {
+        piecemeal_gubbins.push("27".into());
+    }
+    if styles_to_set.contains(Style::ITALIC) {
+        piecemeal_gubbins.push("3".into());
+    } else if styles_to_clear.contains(Style::ITALIC) {
+        piecemeal_gubbins.push("23".into());
+    }
+    if styles_to_set.contains(Style::BLINK) {
+        piecemeal_gubbins.push("5".into());
+    } else if styles_to_clear.contains(Style::BLINK) {
+        piecemeal_gubbins.push("25".into());
+    }
+    if styles_to_set.contains(Style::CONCEAL) {
+        piecemeal_gubbins.push("8".into());
+    } else if styles_to_clear.contains(Style::CONCEAL) {
+        piecemeal_gubbins.push("28".into());
+    }
+    if styles_to_set.contains(Style::STRIKETHROUGH) {
+        piecemeal_gubbins.push("9".into());
+    } else if styles_to_clear.contains(Style::STRIKETHROUGH) {
+        piecemeal_gubbins.push("29".into());
+    }
+    if styles_to_set.contains(Style::OVERLINE) {
+        piecemeal_gubbins.push("53".into());
+    } else if styles_to_clear.contains(Style::OVERLINE) {
+        piecemeal_gubbins.push("55".into());
+    }
+    if fg != old_fg {
+        piecemeal_gubbins
+            .push(fg.map(|x| x.as_ansi_fg()).unwrap_or("39".into()));
+    }
+    if bg != old_bg {
+        piecemeal_gubbins
+            .push(bg.map(|x| x.as_ansi_bg()).unwrap_or("49".into()));
+    }
+    if piecemeal_gubbins.is_empty() {
+        return String::new();
+    }
+    let piecemeal = gubbins_to_sequence(&piecemeal_gubbins);
+    let mut flockmeal_gubbins: Vec<Cow<str>> = Vec::with_capacity(13);
+    flockmeal_gubbins.push("0".into());
+    if style.contains(Style::BOLD) {
+        flockmeal_gubbins.push("1".into());
+    }
+    if style.contains(Style::DIM) {
+        flockmeal_gubbins.push("2".into());
+    }
+    if style.contains(Style::UNDERLINE) {
+        flockmeal_gubbins.push("4".into());
+    }
+    if style.contains(Style::DOUBLE_UNDERLINE) {
+        flockmeal_gubbins.push("21".into());
+    }
+    if style.contains(Style::INVERSE) {
+        flockmeal_gubbins.push("7".into());
+    }
+    if style.contains(Style::ITALIC) {
+        flockmeal_gubbins.push("3".into());
+    }
+    if style.contains(Style::BLINK) {
+        flockmeal_gubbins.push("5".into());
+    }
+    if style.contains(Style::CONCEAL) {
+        flockmeal_gubbins.push("8".into());
+    }
+    if style.contains(Style::STRIKETHROUGH) {
+        flockmeal_gubbins.push("9".into());
+    }
+    if style.contains(Style::OVERLINE) {
+        flockmeal_gubbins.push("53".into());
+    }
+    if let Some(fg) = fg {
+        flockmeal_gubbins.push(fg.as_ansi_fg());
+    }
+    if let Some(bg) = bg {
+        flockmeal_gubbins.push(bg.as_ansi_bg());
+    }
+    let flockmeal = gubbins_to_sequence(&flockmeal_gubbins);
+    if flockmeal.len() <= piecemeal.len() {
+        // flockmeal should win all else being equal, as it is slightly less
+        // likely to go wrong
+        flockmeal
+    } else {
+        piecemeal
+    }
+}
+
+fn gubbins_to_sequence(gubbins: &[Cow<str>]) -> String {
+    if gubbins.is_empty() {
+        return String::new();
+    }
+    let mut ret = String::with_capacity(
+        gubbins.iter().map(|x| x.len() + 1).sum::<usize>() + 2,
+    );
+    ret.push('\x1B');
+    for (i, gubbin) in gubbins.iter().enumerate() {
+        if i == 0 {
+            ret.push('[');
+        } else {
+            ret.push(';');
+        };
+        ret += gubbin;
+    }
+    ret.push('m');
+    ret
+}
+
+/// A link containing control characters would let its URL escape the OSC
+/// sequence. Don't link it at all.
+pub(crate) fn sanitize_link(link: Option<&str>) -> Option<&str> {
+    link.filter(|x| !x.chars().any(|x| x.is_control()))
+}
+
+/// Returns the OSC 8 sequence that starts linking to the given URL, or stops
+/// linking if `None`.
+pub(crate) fn link_sequence(link: Option<&str>) -> String {
+    format!("\x1B]8;;{}\x1B\\", link.unwrap_or(""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn ansi_round_trip() {
+        let line = liso!(
+            "plain ",
+            bold + underline,
+            fg = red,
+            "loud",
+            -bold,
+            double_underline,
+            bg = indexed(200),
+            " doubled",
+            link = "https://example.com/",
+            plain,
+            bg = none,
+            fg = rgb(1, 2, 3),
+            " link",
+            link = none,
+            reset,
+            " done"
+        );
+        let ansi = line.to_ansi_string();
+        assert_eq!(
+            ansi,
+            "plain \x1B[1;4;31mloud\x1B[22;21;48;5;200m doubled\
+             \x1B[0;38;2;1;2;3m\x1B]8;;https://example.com/\x1B\\ link\
+             \x1B[0m\x1B]8;;\x1B\\ done"
+        );
+        assert_eq!(liso!(ansi ansi.as_str()), line);
+        assert_eq!(format!("{:#}", line), ansi);
+        assert_eq!(format!("{}", line), line.as_str());
+        assert_eq!(Line::new().to_ansi_string(), "");
+    }
+}
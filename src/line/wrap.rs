@@ -0,0 +1,253 @@
+use super::*;
+
+use textwrap::core::{break_words, Word};
+use textwrap::word_splitters::split_words;
+use textwrap::{WordSplitter, WrapAlgorithm};
+
+/// How [`wrapln_with`][1] and [`Line::wrap_to_width_with`][2] decide where
+/// a line may be broken.
+///
+/// [1]: struct.Output.html#method.wrapln_with
+/// [2]: struct.Line.html#method.wrap_to_width_with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WordSeparator {
+    /// Only break lines at runs of ASCII spaces.
+    AsciiSpace,
+    /// Break lines wherever the Unicode line breaking algorithm allows, which
+    /// includes between most CJK characters and after some punctuation. This
+    /// is the default.
+    #[default]
+    UnicodeBreakProperties,
+}
+
+/// Options for wrapping a [`Line`](struct.Line.html) to the width of the
+/// terminal, for use with [`wrapln_with`][1] or
+/// [`Line::wrap_to_width_with`][2]. Only available with the `wrap` feature,
+/// which is enabled by default.
+///
+/// ```rust
+/// # use liso::{liso, WrapOptions};
+/// let options = WrapOptions::new()
+///     .initial_indent(liso!(dim, "12:34:56 "))
+///     .subsequent_indent("         ");
+/// let mut line = liso!("Reticulating splines, please wait.");
+/// line.wrap_to_width_with(30, &options);
+/// assert_eq!(line, liso!(dim, "12:34:56 ", plain,
+///                        "Reticulating splines,\n         please wait."));
+/// ```
+///
+/// [1]: struct.Output.html#method.wrapln_with
+/// [2]: struct.Line.html#method.wrap_to_width_with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrapOptions {
+    initial_indent: Line,
+    subsequent_indent: Line,
+    break_words: bool,
+    word_separator: WordSeparator,
+}
+
+impl WrapOptions {
+    /// Options that wrap the same way [`wrapln`][1] does: no indentation,
+    /// words longer than a line are broken, and lines are broken according
+    /// to the Unicode line breaking algorithm.
+    ///
+    /// [1]: struct.Output.html#method.wrapln
+    pub fn new() -> WrapOptions {
+        WrapOptions {
+            initial_indent: Line::new(),
+            subsequent_indent: Line::new(),
+            break_words: true,
+            word_separator: WordSeparator::default(),
+        }
+    }
+    /// Text to put at the start of the first line. Its width is taken into
+    /// account when wrapping.
+    pub fn initial_indent<T>(mut self, indent: T) -> WrapOptions
+    where
+        T: Into<Line>,
+    {
+        self.initial_indent = indent.into();
+        self
+    }
+    /// Text to put at the start of every line after the first, whether that
+    /// line was made by wrapping or was already there. Its width is taken
+    /// into account when wrapping.
+    pub fn subsequent_indent<T>(mut self, indent: T) -> WrapOptions
+    where
+        T: Into<Line>,
+    {
+        self.subsequent_indent = indent.into();
+        self
+    }
+    /// Whether to break up words that are too long to fit on a line by
+    /// themselves (the default), or let them stick out past the end.
+    pub fn break_words(mut self, break_words: bool) -> WrapOptions {
+        self.break_words = break_words;
+        self
+    }
+    /// How to decide where lines may be broken.
+    pub fn word_separator(mut self, separator: WordSeparator) -> WrapOptions {
+        self.word_separator = separator;
+        self
+    }
+}
+
+impl Default for WrapOptions {
+    fn default() -> WrapOptions {
+        WrapOptions::new()
+    }
+}
+
+impl Line {
+    /// Insert linebreaks as necessary to make it so that no line within this
+    /// `Line` is wider than the given number of columns, and indent the lines
+    /// as the given [`WrapOptions`](struct.WrapOptions.html) say to. Only
+    /// available with the `wrap` feature, which is enabled by default.
+    ///
+    /// As with [`wrap_to_width`](#method.wrap_to_width), you probably want to
+    /// use [`wrapln_with`](struct.Output.html#method.wrapln_with) instead,
+    /// which will wrap to the width of the user's terminal.
+    pub fn wrap_to_width_with(&mut self, width: usize, options: &WrapOptions) {
+        assert!(width > 0);
+        let initial_width =
+            width.saturating_sub(options.initial_indent.display_width());
+        let subsequent_width =
+            width.saturating_sub(options.subsequent_indent.display_width());
+        let separator = match options.word_separator {
+            WordSeparator::AsciiSpace => textwrap::WordSeparator::AsciiSpace,
+            WordSeparator::UnicodeBreakProperties => {
+                textwrap::WordSeparator::UnicodeBreakProperties
+            }
+        };
+        let splitter = WordSplitter::HyphenSplitter;
+        let algorithm = WrapAlgorithm::new();
+        // Byte ranges of whitespace to replace with a newline (and the
+        // subsequent indent), including the newlines already there.
+        let mut breaks = vec![];
+        let mut paragraph_start = 0;
+        for (n, paragraph) in self.text.split('\n').enumerate() {
+            let line_widths = if n == 0 {
+                [initial_width, subsequent_width]
+            } else {
+                breaks.push(paragraph_start - 1..paragraph_start);
+                [subsequent_width, subsequent_width]
+            };
+            let words =
+                split_words(separator.find_words(paragraph), &splitter);
+            let words = if options.break_words {
+                let mut words = break_words(words, subsequent_width);
+                if n == 0 && !options.initial_indent.is_empty() {
+                    // (otherwise, the first word would always end up on the
+                    // first line, even if it was broken to fit a different
+                    // width; this is what `textwrap::wrap` does too)
+                    words.insert(0, Word::from(""));
+                }
+                words
+            } else {
+                words.collect()
+            };
+            // textwrap guarantees that the words are contiguous, so we can
+            // find each line just by adding up lengths
+            let mut pos = paragraph_start;
+            let mut prev_whitespace = 0;
+            for (i, line) in
+                algorithm.wrap(&words, &line_widths).into_iter().enumerate()
+            {
+                if i != 0 {
+                    breaks.push(pos - prev_whitespace..pos);
+                }
+                let Some(last) = line.last() else { continue };
+                pos += line
+                    .iter()
+                    .map(|word| word.len() + word.whitespace.len())
+                    .sum::<usize>();
+                prev_whitespace = last.whitespace.len();
+            }
+            paragraph_start += paragraph.len() + 1;
+        }
+        if breaks.is_empty() && options.initial_indent.is_empty() {
+            return;
+        }
+        let mut ret = options.initial_indent.clone();
+        let mut pos = 0;
+        for range in breaks.into_iter() {
+            ret.append_line(&self.slice(pos..range.start));
+            // the newline looks like the whitespace it replaces
+            ret.restore_attrs(&self.attrs_at(range.start));
+            ret.add_text("\n");
+            ret.append_line(&options.subsequent_indent);
+            pos = range.end;
+        }
+        ret.append_line(&self.slice(pos..));
+        *self = ret;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn wrap_with_options() {
+        let options = WrapOptions::new()
+            .initial_indent(liso!(fg = blue, "> "))
+            .subsequent_indent(liso!(bold, ". "));
+        let mut line = liso!(
+            "one two ",
+            underline,
+            "three four",
+            plain,
+            "\nfive\n\nsupercalifragilistic"
+        );
+        line.wrap_to_width_with(10, &options);
+        assert_eq!(
+            line,
+            liso!(
+                fg = blue,
+                "> ",
+                reset,
+                "one two\n",
+                bold,
+                ". ",
+                reset,
+                underline,
+                "three\n",
+                reset,
+                bold,
+                ". ",
+                reset,
+                underline,
+                "four",
+                plain,
+                "\n",
+                bold,
+                ". ",
+                plain,
+                "five\n",
+                bold,
+                ". ",
+                plain,
+                "\n",
+                bold,
+                ". ",
+                plain,
+                "supercal\n",
+                bold,
+                ". ",
+                plain,
+                "ifragili\n",
+                bold,
+                ". ",
+                plain,
+                "stic"
+            )
+        );
+        let mut line = liso!("a supercalifragilistic word");
+        line.wrap_to_width_with(
+            10,
+            &WrapOptions::new()
+                .break_words(false)
+                .word_separator(WordSeparator::AsciiSpace),
+        );
+        assert_eq!(line, liso!("a\nsupercalifragilistic\nword"));
+    }
+}
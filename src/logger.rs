@@ -0,0 +1,173 @@
+//! A backend for the `log` crate, so that log messages don't scramble the
+//! prompt.
+
+use super::*;
+
+use std::time::SystemTime;
+
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Prints records from the [`log`](https://docs.rs/log) crate through Liso,
+/// as styled output. Requires the `log` feature.
+///
+/// Each record looks like this:
+///
+/// ```text
+/// [2026-01-02T03:04:05.678Z  INFO my_program::net] Connected to server
+/// ```
+///
+/// The level is styled using the theme entry named after it (`error`,
+/// `warning`, `info`, `debug`, or `trace`), and the timestamp and target are
+/// styled with `muted`. (See [`Theme`](struct.Theme.html).) Timestamps are in
+/// UTC.
+///
+/// ```rust,no_run
+/// # let io = liso::InputOutput::new();
+/// liso::Logger::new(&io)
+///     .level(log::LevelFilter::Debug)
+///     .show_timestamp(false)
+///     .init()
+///     .unwrap();
+/// log::info!("Hello World!");
+/// ```
+///
+/// If a record is logged after Liso has shut down, it's written to stderr
+/// instead, unstyled.
+pub struct Logger {
+    output: OutputOnly,
+    level: LevelFilter,
+    show_target: bool,
+    show_timestamp: bool,
+}
+
+impl Logger {
+    /// Creates a logger that prints through the given `Output`. By default,
+    /// it shows records of level `Info` and above, with their target and a
+    /// timestamp.
+    pub fn new(output: &Output) -> Logger {
+        Logger {
+            output: output.clone_output(),
+            level: LevelFilter::Info,
+            show_target: true,
+            show_timestamp: true,
+        }
+    }
+    /// Sets the most verbose level of record that will be shown.
+    pub fn level(mut self, level: LevelFilter) -> Logger {
+        self.level = level;
+        self
+    }
+    /// Sets whether each record's target (usually the module it came from)
+    /// is shown.
+    pub fn show_target(mut self, show_target: bool) -> Logger {
+        self.show_target = show_target;
+        self
+    }
+    /// Sets whether each record is shown with a timestamp.
+    pub fn show_timestamp(mut self, show_timestamp: bool) -> Logger {
+        self.show_timestamp = show_timestamp;
+        self
+    }
+    /// Installs this logger as the global logger, and sets the global
+    /// maximum level to match. Fails if a global logger has already been
+    /// installed.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+    /// Formats a record the way it will be printed.
+    fn format(&self, time: SystemTime, record: &Record) -> Line {
+        let mut ret = Line::new();
+        liso_add!(ret, "[");
+        if self.show_timestamp {
+            liso_add!(
+                ret,
+                theme = "muted",
+                format_timestamp(time),
+                reset,
+                " "
+            );
+        }
+        let (theme, name) = match record.level() {
+            Level::Error => ("error", "ERROR"),
+            Level::Warn => ("warning", " WARN"),
+            Level::Info => ("info", " INFO"),
+            Level::Debug => ("debug", "DEBUG"),
+            Level::Trace => ("trace", "TRACE"),
+        };
+        liso_add!(ret, theme = theme, name, reset);
+        if self.show_target {
+            liso_add!(ret, " ", theme = "muted", record.target(), reset);
+        }
+        liso_add!(ret, "] ", format!("{}", record.args()));
+        ret
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = self.format(SystemTime::now(), record);
+        if let Err(std_mpsc::SendError(Request::Output(line))) =
+            self.output.0.tx.send(Request::Output(line))
+        {
+            eprintln!("{}", line.as_str());
+        }
+    }
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+    #[test]
+    fn logger_format() {
+        let (output, _rx) = Output::for_test();
+        let logger = Logger::new(&output);
+        // 2001-09-09T01:46:40.250Z
+        let time = UNIX_EPOCH + Duration::from_millis(1_000_000_000_250);
+        let line = logger.format(
+            time,
+            &Record::builder()
+                .args(format_args!("Connected"))
+                .level(Level::Warn)
+                .target("app::net")
+                .build(),
+        );
+        assert_eq!(
+            line,
+            liso!(
+                "[",
+                theme = "muted",
+                "2001-09-09T01:46:40.250Z",
+                reset,
+                " ",
+                theme = "warning",
+                " WARN",
+                reset,
+                " ",
+                theme = "muted",
+                "app::net",
+                reset,
+                "] Connected"
+            )
+        );
+        let logger = logger.show_target(false).show_timestamp(false);
+        let line = logger.format(
+            time,
+            &Record::builder()
+                .args(format_args!("Oops"))
+                .level(Level::Error)
+                .build(),
+        );
+        assert_eq!(line.as_str(), "[ERROR] Oops");
+    }
+}
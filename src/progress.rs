@@ -0,0 +1,346 @@
+//! Progress bars and spinners. They live in the status area, and their
+//! handles update them through shared state instead of through the request
+//! channel, so updating them as often as you like is cheap.
+
+use super::*;
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+/// How long each frame of a spinner's animation lasts.
+pub(crate) const SPINNER_INTERVAL: Duration = Duration::from_millis(125);
+
+/// The frames of a spinner's animation. Plain ASCII, so that it works on
+/// every terminal we support.
+const SPINNER_FRAMES: [&str; 4] = ["-", "\\", "|", "/"];
+
+/// The bar never gets narrower than this, even if that means the rest of the
+/// row gets cut off.
+const MIN_BAR_WIDTH: usize = 10;
+
+#[derive(Debug)]
+struct ProgressState {
+    message: Line,
+    position: u64,
+    /// `None` for a spinner.
+    total: Option<u64>,
+    started: Instant,
+}
+
+/// The part of a progress indicator that is shared between its handles and
+/// the worker.
+#[derive(Debug)]
+pub(crate) struct ProgressShared {
+    state: Mutex<ProgressState>,
+    /// Set when the state has changed since the worker last drew it. While
+    /// this is set, further changes don't need to tell the worker anything.
+    dirty: AtomicBool,
+}
+
+impl ProgressShared {
+    fn new(message: Line, total: Option<u64>) -> Arc<ProgressShared> {
+        Arc::new(ProgressShared {
+            state: Mutex::new(ProgressState {
+                message,
+                position: 0,
+                total,
+                started: Instant::now(),
+            }),
+            dirty: AtomicBool::new(true),
+        })
+    }
+    pub fn is_spinner(&self) -> bool {
+        self.state.lock().unwrap().total.is_none()
+    }
+    /// Renders the indicator as it should look at the given moment, as a
+    /// single row that fills `width` columns (if it can).
+    pub fn render(&self, width: usize, now: Instant) -> Line {
+        self.dirty.store(false, Ordering::Release);
+        let state = self.state.lock().unwrap();
+        let elapsed = now.saturating_duration_since(state.started);
+        let mut ret = Line::new();
+        match state.total {
+            None => {
+                let frame = (elapsed.as_millis()
+                    / SPINNER_INTERVAL.as_millis())
+                    as usize
+                    % SPINNER_FRAMES.len();
+                liso_add!(
+                    ret,
+                    theme = "progress",
+                    SPINNER_FRAMES[frame],
+                    reset
+                );
+                if !state.message.is_empty() {
+                    ret.add_text(" ");
+                    ret.append_line(&state.message);
+                }
+            }
+            Some(total) => {
+                let position = state.position.min(total);
+                let fraction = if total == 0 {
+                    1.0
+                } else {
+                    position as f64 / total as f64
+                };
+                let secs = elapsed.as_secs_f64();
+                let rate = if secs > 0.0 {
+                    state.position as f64 / secs
+                } else {
+                    0.0
+                };
+                let eta = if position >= total {
+                    format_duration(0.0)
+                } else if rate > 0.0 {
+                    format_duration((total - position) as f64 / rate)
+                } else {
+                    "-:--".to_string()
+                };
+                let stats = format!(
+                    " {:>3}% {}/{} {}/s ETA {}",
+                    (fraction * 100.0) as u32,
+                    format_quantity(position as f64),
+                    format_quantity(total as f64),
+                    format_quantity(rate),
+                    eta,
+                );
+                if !state.message.is_empty() {
+                    ret.append_line(&state.message);
+                    ret.reset_all();
+                    ret.add_text(" ");
+                }
+                let bar_width = width
+                    .saturating_sub(ret.display_width() + stats.len() + 2)
+                    .max(MIN_BAR_WIDTH);
+                let filled =
+                    ((bar_width as f64 * fraction) as usize).min(bar_width);
+                let mut bar = "=".repeat(filled);
+                if filled < bar_width && filled > 0 {
+                    bar.pop();
+                    bar.push('>');
+                }
+                liso_add!(
+                    ret,
+                    "[",
+                    theme = "progress",
+                    bar,
+                    reset,
+                    " ".repeat(bar_width - filled),
+                    "]",
+                    stats
+                );
+            }
+        }
+        ret
+    }
+}
+
+/// Formats a number of seconds as `m:ss`, or `h:mm:ss` if it's an hour or
+/// more.
+fn format_duration(secs: f64) -> String {
+    let secs = secs.ceil() as u64;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Formats a quantity compactly, with a metric suffix if it's big.
+fn format_quantity(quantity: f64) -> String {
+    const SUFFIXES: [&str; 6] = ["k", "M", "G", "T", "P", "E"];
+    if quantity < 1000.0 {
+        return format!("{}", quantity as u64);
+    }
+    let mut quantity = quantity / 1000.0;
+    let mut suffix = SUFFIXES[0];
+    for next_suffix in SUFFIXES[1..].iter() {
+        if quantity < 999.95 {
+            break;
+        }
+        quantity /= 1000.0;
+        suffix = next_suffix;
+    }
+    format!("{:.1}{}", quantity, suffix)
+}
+
+/// A handle to a progress bar in the status area. Create one with
+/// [`Output::progress_bar`][1].
+///
+/// The bar shows its message, followed by the bar itself, the percentage
+/// complete, the position and total, the throughput (units per second), and
+/// an estimate of the time remaining. Everything is measured from when the
+/// bar was created.
+///
+/// Handles can be cloned and sent to other threads. All clones update the
+/// same bar. Updates are cheap: they change some shared state, and only wake
+/// up Liso if it has already drawn the previous update. This means you can
+/// update the bar every time you process an item, however often that is.
+///
+/// The bar stays on the screen until [`finish`](#method.finish) is called,
+/// even if every handle is dropped.
+///
+/// [1]: struct.Output.html#method.progress_bar
+#[derive(Clone)]
+pub struct ProgressBar {
+    shared: Arc<ProgressShared>,
+    key: String,
+    output: OutputOnly,
+}
+
+impl ProgressBar {
+    pub(crate) fn new(
+        output: OutputOnly,
+        key: String,
+        message: Line,
+        total: u64,
+    ) -> ProgressBar {
+        let shared = ProgressShared::new(message, Some(total));
+        output.send(Request::Progress(key.clone(), shared.clone()));
+        ProgressBar {
+            shared,
+            key,
+            output,
+        }
+    }
+    fn update<F: FnOnce(&mut ProgressState)>(&self, f: F) {
+        f(&mut self.shared.state.lock().unwrap());
+        changed(&self.shared, &self.output);
+    }
+    /// Sets how many units of work have been done.
+    pub fn set_position(&self, position: u64) {
+        self.update(|state| state.position = position)
+    }
+    /// Adds to the number of units of work that have been done.
+    pub fn inc(&self, delta: u64) {
+        self.update(|state| {
+            state.position = state.position.saturating_add(delta)
+        })
+    }
+    /// Returns how many units of work have been done.
+    pub fn position(&self) -> u64 {
+        self.shared.state.lock().unwrap().position
+    }
+    /// Sets how many units of work there are in total.
+    pub fn set_total(&self, total: u64) {
+        self.update(|state| state.total = Some(total))
+    }
+    /// Replaces the (possibly styled) message shown before the bar.
+    pub fn set_message<T>(&self, message: T)
+    where
+        T: Into<Line>,
+    {
+        let message = message.into();
+        self.update(|state| state.message = message)
+    }
+    /// Removes the bar from the status area, along with its status slot.
+    /// Clones of this handle can still be updated, but nothing will be
+    /// displayed.
+    pub fn finish(&self) {
+        self.output.send(Request::RemoveProgress(
+            self.key.clone(),
+            self.shared.clone(),
+        ))
+    }
+}
+
+/// A handle to a spinner in the status area: an animated indicator for work
+/// that doesn't have a known end. Create one with [`Output::spinner`][1].
+///
+/// Liso animates the spinner on its own, so you only need to touch the handle
+/// to change the message, or to get rid of the spinner when the work is done.
+/// Handles can be cloned and sent to other threads, the same as
+/// [`ProgressBar`](struct.ProgressBar.html)s.
+///
+/// The spinner stays on the screen until [`finish`](#method.finish) is called,
+/// even if every handle is dropped.
+///
+/// [1]: struct.Output.html#method.spinner
+#[derive(Clone)]
+pub struct Spinner {
+    shared: Arc<ProgressShared>,
+    key: String,
+    output: OutputOnly,
+}
+
+impl Spinner {
+    pub(crate) fn new(
+        output: OutputOnly,
+        key: String,
+        message: Line,
+    ) -> Spinner {
+        let shared = ProgressShared::new(message, None);
+        output.send(Request::Progress(key.clone(), shared.clone()));
+        Spinner {
+            shared,
+            key,
+            output,
+        }
+    }
+    /// Replaces the (possibly styled) message shown after the spinner.
+    pub fn set_message<T>(&self, message: T)
+    where
+        T: Into<Line>,
+    {
+        self.shared.state.lock().unwrap().message = message.into();
+        changed(&self.shared, &self.output);
+    }
+    /// Removes the spinner from the status area, along with its status slot.
+    pub fn finish(&self) {
+        self.output.send(Request::RemoveProgress(
+            self.key.clone(),
+            self.shared.clone(),
+        ))
+    }
+}
+
+/// Lets the worker know that an indicator needs redrawing, unless it already
+/// knows.
+fn changed(shared: &ProgressShared, output: &Output) {
+    if !shared.dirty.swap(true, Ordering::AcqRel) {
+        output.send(Request::ProgressChanged)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn progress_rendering() {
+        let shared = ProgressShared::new(liso!(bold, "Copying"), Some(4000));
+        let started = shared.state.lock().unwrap().started;
+        shared.state.lock().unwrap().position = 1000;
+        let line = shared.render(60, started + Duration::from_secs(2));
+        assert!(!shared.dirty.load(Ordering::Acquire));
+        assert_eq!(
+            line,
+            liso!(
+                bold,
+                "Copying",
+                reset,
+                " [",
+                theme = "progress",
+                "====>",
+                reset,
+                "               ]  25% 1.0k/4.0k 500/s ETA 0:06"
+            )
+        );
+        assert_eq!(line.display_width(), 60);
+        let shared = ProgressShared::new(liso!("Thinking"), None);
+        let started = shared.state.lock().unwrap().started;
+        let line = shared.render(60, started + SPINNER_INTERVAL * 2);
+        assert_eq!(line, liso!(theme = "progress", "|", reset, " Thinking"));
+    }
+    #[test]
+    fn progress_formatting() {
+        assert_eq!(format_duration(0.0), "0:00");
+        assert_eq!(format_duration(61.5), "1:02");
+        assert_eq!(format_duration(3661.0), "1:01:01");
+        assert_eq!(format_quantity(999.0), "999");
+        assert_eq!(format_quantity(1234.0), "1.2k");
+        assert_eq!(format_quantity(999_999.0), "1.0M");
+        assert_eq!(format_quantity(2.5e9), "2.5G");
+    }
+}
@@ -0,0 +1,184 @@
+//! The status area: any number of named status slots, displayed below the
+//! status line and above the prompt.
+
+use super::*;
+
+use std::{collections::HashMap, sync::Arc};
+
+/// Where a status slot's text goes within its row. See
+/// [`Output::set_status_slot_layout`][1].
+///
+/// [1]: struct.Output.html#method.set_status_slot_layout
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StatusAlign {
+    /// Against the left edge of the terminal. This is the default.
+    #[default]
+    Left,
+    /// Centered within the terminal.
+    Center,
+    /// Against the right edge of the terminal.
+    Right,
+}
+
+/// What is displayed in a status slot.
+#[derive(Debug)]
+enum SlotContent {
+    Line(Line),
+    /// A `ProgressBar` or `Spinner`, drawn fresh every time.
+    Progress(Arc<ProgressShared>),
+}
+
+#[derive(Debug)]
+struct StatusSlot {
+    /// `None` if the layout was set but there's nothing to display yet.
+    content: Option<SlotContent>,
+    order: i32,
+    align: StatusAlign,
+}
+
+/// All of the status slots. Each `set_*` and `remove` method returns true if
+/// the status area changed.
+#[derive(Debug, Default)]
+pub(crate) struct StatusArea {
+    slots: HashMap<String, StatusSlot>,
+}
+
+impl StatusArea {
+    pub fn set_line(&mut self, key: String, line: Line) -> bool {
+        let slot = self.slot_mut(key);
+        match slot.content.as_ref() {
+            Some(SlotContent::Line(old_line)) if old_line == &line => false,
+            _ => {
+                slot.content = Some(SlotContent::Line(line));
+                true
+            }
+        }
+    }
+    pub fn set_progress(&mut self, key: String, shared: Arc<ProgressShared>) {
+        self.slot_mut(key).content = Some(SlotContent::Progress(shared));
+    }
+    pub fn set_layout(
+        &mut self,
+        key: String,
+        order: i32,
+        align: StatusAlign,
+    ) -> bool {
+        let slot = self.slot_mut(key);
+        if slot.order == order && slot.align == align {
+            false
+        } else {
+            slot.order = order;
+            slot.align = align;
+            // (only matters if there's something to lay out)
+            slot.content.is_some()
+        }
+    }
+    pub fn remove(&mut self, key: &str) -> bool {
+        match self.slots.remove(key) {
+            Some(slot) => slot.content.is_some(),
+            None => false,
+        }
+    }
+    /// Removes the status slot with the given key, but only if it's still
+    /// displaying the given progress indicator.
+    pub fn remove_progress(
+        &mut self,
+        key: &str,
+        shared: &Arc<ProgressShared>,
+    ) -> bool {
+        match self.slots.get(key).and_then(|slot| slot.content.as_ref()) {
+            Some(SlotContent::Progress(x)) if Arc::ptr_eq(x, shared) => {
+                self.remove(key)
+            }
+            _ => false,
+        }
+    }
+    /// Returns true if any spinners are being displayed, meaning that the
+    /// status area needs to be redrawn periodically to animate them.
+    pub fn has_spinners(&self) -> bool {
+        self.slots.values().any(|slot| {
+            matches!(&slot.content,
+                     Some(SlotContent::Progress(x)) if x.is_spinner())
+        })
+    }
+    fn slot_mut(&mut self, key: String) -> &mut StatusSlot {
+        self.slots.entry(key).or_insert(StatusSlot {
+            content: None,
+            order: 0,
+            align: StatusAlign::default(),
+        })
+    }
+    /// Appends the status area to the given `Line`, each row followed by a
+    /// linebreak. Rows are cut down to fit within `width` columns, so that
+    /// alignment works.
+    pub fn render_into(&self, out: &mut Line, width: usize) {
+        let mut slots: Vec<(&String, &StatusSlot)> = self
+            .slots
+            .iter()
+            .filter(|(_, slot)| slot.content.is_some())
+            .collect();
+        slots.sort_by(|(a_key, a), (b_key, b)| {
+            a.order.cmp(&b.order).then_with(|| a_key.cmp(b_key))
+        });
+        let now = Instant::now();
+        for (_, slot) in slots.into_iter() {
+            let rows: Vec<Line> = match slot.content.as_ref().unwrap() {
+                SlotContent::Line(line) if line.is_empty() => {
+                    vec![Line::new()]
+                }
+                SlotContent::Line(line) => line.lines().collect(),
+                SlotContent::Progress(shared) => {
+                    vec![shared.render(width, now)]
+                }
+            };
+            for mut row in rows.into_iter() {
+                row.truncate_to_width(width, "…");
+                let room = width - row.display_width();
+                let padding = match slot.align {
+                    StatusAlign::Left => 0,
+                    StatusAlign::Center => room / 2,
+                    StatusAlign::Right => room,
+                };
+                out.set_link(None);
+                out.add_text(" ".repeat(padding));
+                out.append_line(&row);
+                out.set_link(None);
+                out.reset_and_break();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn status_area_layout() {
+        let mut area = StatusArea::default();
+        assert!(area.set_line("net".to_string(), liso!(fg = green, "up")));
+        assert!(!area.set_line("net".to_string(), liso!(fg = green, "up")));
+        assert!(!area.set_layout("disk".to_string(), -1, StatusAlign::Right));
+        assert!(area.set_line("disk".to_string(), liso!("disk: 12%")));
+        assert!(area.set_line("cpu".to_string(), liso!("a\nlong line")));
+        assert!(area.set_layout("cpu".to_string(), 0, StatusAlign::Center));
+        let mut out = Line::new();
+        area.render_into(&mut out, 8);
+        assert_eq!(
+            out,
+            liso!(
+                "disk: 1…\n",
+                "   a\n",
+                "long li…\n",
+                fg = green,
+                "up\n",
+                reset
+            )
+        );
+        assert!(area.remove("cpu"));
+        assert!(!area.remove("cpu"));
+        let mut out = Line::new();
+        area.render_into(&mut out, 8);
+        assert_eq!(out.as_str(), "disk: 1…\nup\n");
+    }
+}
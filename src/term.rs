@@ -1,6 +1,6 @@
 use super::*;
 
-use std::io::{Read, Stdout};
+use std::io::{Read, Write};
 
 mod ansi;
 use ansi::AnsiTerminal;
@@ -23,6 +23,9 @@ pub(crate) trait Term {
         bg: Option<Color>,
     ) -> LifeOrDeath;
     fn reset_attrs(&mut self) -> LifeOrDeath;
+    /// Make subsequently-printed text link to the given URL, or stop linking
+    /// if `None`. Terminals that can't display links will ignore this.
+    fn set_link(&mut self, link: Option<&str>) -> LifeOrDeath;
     fn print(&mut self, text: &str) -> LifeOrDeath;
     fn print_char(&mut self, char: char) -> LifeOrDeath;
     fn print_spaces(&mut self, spaces: usize) -> LifeOrDeath;
@@ -39,16 +42,135 @@ pub(crate) trait Term {
     fn hide_cursor(&mut self) -> LifeOrDeath;
     fn show_cursor(&mut self) -> LifeOrDeath;
     fn get_width(&mut self) -> u32;
+    fn get_height(&mut self) -> u32;
     fn cur_style(&self) -> Style;
     fn flush(&mut self) -> LifeOrDeath;
     fn suspend(&mut self) -> LifeOrDeath;
     fn unsuspend(&mut self) -> LifeOrDeath;
     fn cleanup(&mut self) -> LifeOrDeath;
+    /// Stop reading from stdin, so that whoever has the terminal while we're
+    /// suspended can read from it instead. Returns false if we can't.
+    fn pause_input(&mut self) -> bool {
+        false
+    }
+    /// Start reading from stdin again, after a successful `pause_input`.
+    fn resume_input(&mut self) {}
+    /// Start recording everything sent to (and, if requested, received
+    /// from) the terminal, or stop if `None`. Returns false if this kind of
+    /// terminal can't be recorded.
+    fn set_cast_recording(
+        &mut self,
+        _recording: Option<CastRecording>,
+    ) -> Result<bool, DummyError> {
+        Ok(false)
+    }
+    /// If recording stopped because of an error, returns that error (once).
+    fn take_cast_error(&mut self) -> Option<std::io::Error> {
+        None
+    }
+}
+
+/// Writes to wherever the terminal really is. Usually that's stdout, but
+/// not while stdout is being captured.
+#[cfg(feature = "capture-stdout")]
+pub(crate) use crate::capture::RealStdout;
+#[cfg(not(feature = "capture-stdout"))]
+pub(crate) type RealStdout = std::io::Stdout;
+
+pub(crate) fn real_stdout() -> RealStdout {
+    #[cfg(feature = "capture-stdout")]
+    return RealStdout::new();
+    #[cfg(not(feature = "capture-stdout"))]
+    return std::io::stdout();
+}
+
+/// Returns the size of the terminal, as `(columns, rows)`, if we can tell.
+pub(crate) fn terminal_size() -> Option<(u32, u32)> {
+    #[cfg(all(unix, feature = "capture-stdout"))]
+    {
+        // `termsize` only looks at stdout, which might be captured
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let fd = crate::capture::real_stdout_fd();
+        if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } != 0 {
+            return None;
+        }
+        Some((size.ws_col as u32, size.ws_row as u32))
+    }
+    #[cfg(not(all(unix, feature = "capture-stdout")))]
+    termsize::get().map(|x| (x.cols as u32, x.rows as u32))
+}
+
+/// Puts captured stdout and stderr (if any) back the way they were, so that
+/// whoever has the terminal while we're suspended can use them.
+pub(crate) fn pause_captures() {
+    #[cfg(any(feature = "capture-stderr", feature = "capture-stdout"))]
+    crate::capture::pause_captures();
+}
+
+/// Undoes `pause_captures`.
+pub(crate) fn resume_captures() {
+    #[cfg(any(feature = "capture-stderr", feature = "capture-stdout"))]
+    crate::capture::resume_captures();
+}
+
+/// Standard output, plus a cast recording to copy it into. Bytes are held
+/// until the next flush, so that each flush becomes (at most) one event.
+pub(crate) struct TermOutput {
+    stdout: RealStdout,
+    cast: SharedCast,
+    /// Whether to bother holding onto bytes for the recording.
+    recording: bool,
+    pending: Vec<u8>,
+}
+
+impl TermOutput {
+    pub fn new(cast: SharedCast) -> TermOutput {
+        TermOutput {
+            stdout: real_stdout(),
+            cast,
+            recording: false,
+            pending: Vec::new(),
+        }
+    }
+    pub fn set_cast_recording(
+        &mut self,
+        recording: Option<CastRecording>,
+        size: (u32, u32),
+    ) -> std::io::Result<()> {
+        self.flush()?;
+        let mut cast = self.cast.lock().unwrap();
+        cast.set(recording, size);
+        self.recording = cast.is_recording();
+        Ok(())
+    }
+}
+
+impl Write for TermOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let amt = self.stdout.write(buf)?;
+        if self.recording {
+            self.pending.extend_from_slice(&buf[..amt]);
+        }
+        Ok(amt)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stdout.flush()?;
+        if self.recording {
+            let size = terminal_size().unwrap_or((80, 24));
+            let mut cast = self.cast.lock().unwrap();
+            cast.output(&self.pending, size);
+            // (the input thread might have stopped the recording)
+            self.recording = cast.is_recording();
+            self.pending.clear();
+        }
+        Ok(())
+    }
 }
 
 pub(crate) fn new_term(
     req_tx: &std_mpsc::Sender<Request>,
 ) -> Result<Box<dyn Term>, DummyError> {
+    let color_depth = ColorDepth::detect();
     if let Ok(term) = std::env::var("TERM") {
         let main = term.split('-').next().unwrap_or("");
         match main {
@@ -66,10 +188,7 @@ pub(crate) fn new_term(
                     // - 40 x 25: low res = 16 colors
                     // Anything else is a misconfiguration, so we just
                     // assume monochrome to be safe.
-                    match termsize::get()
-                        .map(|x| (x.cols as u32, x.rows as u32))
-                        .unwrap_or((80, 50))
-                    {
+                    match terminal_size().unwrap_or((80, 50)) {
                         (80, 50) => 2,
                         (80, 25) => 4,
                         (40, 25) => 16,
@@ -84,10 +203,14 @@ pub(crate) fn new_term(
                 } else {
                     16
                 };
-                return Ok(Box::new(Vt52::new(req_tx.clone(), num_colors)?));
+                return Ok(Box::new(Vt52::new(
+                    req_tx.clone(),
+                    num_colors,
+                    color_depth,
+                )?));
             }
             _ => (), // fall through
         }
     }
-    Ok(Box::new(AnsiTerminal::new(req_tx.clone())?))
+    Ok(Box::new(AnsiTerminal::new(req_tx.clone(), color_depth)?))
 }
@@ -5,18 +5,22 @@ use std::{
     panic,
 };
 
-use super::KeyCode;
-
 /// Uses ANSI escape sequences for input and output.
 pub(crate) struct AnsiTerminal {
     suspended: bool,
     old_hook:
         Option<Box<dyn Fn(&panic::PanicHookInfo<'_>) + Sync + Send + 'static>>,
-    stdout: Stdout,
+    stdout: TermOutput,
+    cast: SharedCast,
     cur_style: Style,
     cur_fg: Option<Color>,
     cur_bg: Option<Color>,
+    cur_link: Option<String>,
+    color_depth: ColorDepth,
     input_thread: InterruptibleStdinThread,
+    /// Where the raw stdin thread sends what it reads, kept so that the
+    /// thread can be restarted after `pause_input`.
+    input_tx: std_mpsc::SyncSender<Vec<u8>>,
 }
 
 fn parse_csi_sequence(
@@ -24,13 +28,15 @@ fn parse_csi_sequence(
     req_tx: &mut std_mpsc::Sender<Request>,
 ) -> LifeOrDeath {
     let code = match seq {
-        b"[A" => KeyCode::Up,
-        b"[B" => KeyCode::Down,
-        b"[C" => KeyCode::Right,
-        b"[D" => KeyCode::Left,
-        b"[3~" => KeyCode::Delete,
-        b"[H" => KeyCode::Home,
-        b"[F" => KeyCode::End,
+        b"[A" => Key::Up,
+        b"[B" => Key::Down,
+        b"[C" => Key::Right,
+        b"[D" => Key::Left,
+        b"[3~" => Key::Delete,
+        b"[5~" => Key::PageUp,
+        b"[6~" => Key::PageDown,
+        b"[H" => Key::Home,
+        b"[F" => Key::End,
         _ => return Ok(()), // unknown
     };
     req_tx.send(Request::Key(code))?;
@@ -163,46 +169,60 @@ fn input_thread(
     }
 }
 
+/// Starts the thread that reads raw bytes from stdin.
+fn spawn_stdin_thread(
+    input_tx: std_mpsc::SyncSender<Vec<u8>>,
+    cast: SharedCast,
+) -> InterruptibleStdinThread {
+    let join_handle = std::thread::Builder::new()
+        .name("Liso raw stdin thread".to_owned())
+        .spawn(move || {
+            let stdin = std::io::stdin();
+            let mut stdin = stdin.lock();
+            let mut buf = [0u8; 256];
+            loop {
+                let amt = match stdin.read(&mut buf[..]) {
+                    // as though nothing happened
+                    Err(x) if x.kind() == ErrorKind::Interrupted => continue,
+                    Ok(0) | Err(_) => break,
+                    Ok(x) => x,
+                };
+                cast.lock().unwrap().input(&buf[..amt]);
+                if input_tx.send(buf[..amt].to_owned()).is_err() {
+                    break;
+                }
+            }
+        })
+        .unwrap();
+    InterruptibleStdinThread::new(join_handle)
+}
+
 impl AnsiTerminal {
     pub(crate) fn new(
         req_tx: std_mpsc::Sender<Request>,
+        color_depth: ColorDepth,
     ) -> Result<AnsiTerminal, DummyError> {
         let (input_tx, input_rx) = std_mpsc::sync_channel(1);
+        let cast = SharedCast::default();
         std::thread::Builder::new()
             .name("Liso input processing thread".to_owned())
             .spawn(move || {
                 let _ = input_thread(input_rx, req_tx);
             })
             .unwrap();
-        let input_thread = std::thread::Builder::new()
-            .name("Liso raw stdin thread".to_owned())
-            .spawn(move || {
-                let stdin = std::io::stdin();
-                let mut stdin = stdin.lock();
-                let mut buf = [0u8; 256];
-                loop {
-                    let amt = match stdin.read(&mut buf[..]) {
-                        Err(x) if x.kind() == ErrorKind::Interrupted => {
-                            continue
-                        } // as though nothing happened
-                        Ok(0) | Err(_) => break,
-                        Ok(x) => x,
-                    };
-                    if input_tx.send(buf[..amt].to_owned()).is_err() {
-                        break;
-                    }
-                }
-            })
-            .unwrap();
-        let stdout = std::io::stdout();
+        let input_thread = spawn_stdin_thread(input_tx.clone(), cast.clone());
         let mut ret = AnsiTerminal {
-            stdout,
+            stdout: TermOutput::new(cast.clone()),
+            cast,
             old_hook: None,
             suspended: true,
             cur_style: Style::PLAIN,
             cur_fg: None,
             cur_bg: None,
-            input_thread: InterruptibleStdinThread::new(input_thread),
+            cur_link: None,
+            color_depth,
+            input_thread,
+            input_tx,
         };
         ret.unsuspend()?;
         Ok(ret)
@@ -216,87 +236,32 @@ impl Term for AnsiTerminal {
         fg: Option<Color>,
         bg: Option<Color>,
     ) -> LifeOrDeath {
-        let style = if style.contains(Style::BOLD | Style::DIM) {
-            style - Style::DIM
-        } else {
-            style
-        };
-        let mut piecemeal_gubbins = Vec::with_capacity(7);
-        let styles_to_set = style & !self.cur_style;
-        let styles_to_clear = self.cur_style & !style;
-        if styles_to_set.contains(Style::BOLD) {
-            piecemeal_gubbins.push("1");
-        } else if styles_to_clear.contains(Style::BOLD) {
-            piecemeal_gubbins.push("22"); // NOT 21
-        }
-        if styles_to_set.contains(Style::DIM) {
-            piecemeal_gubbins.push("2");
-        } else if styles_to_clear.contains(Style::DIM)
-            && !style.contains(Style::BOLD)
-        {
-            piecemeal_gubbins.push("22");
-        }
-        if styles_to_set.contains(Style::UNDERLINE) {
-            piecemeal_gubbins.push("4");
-        } else if styles_to_clear.contains(Style::UNDERLINE) {
-            piecemeal_gubbins.push("24");
-        }
-        if styles_to_set.contains(Style::INVERSE) {
-            piecemeal_gubbins.push("7");
-        } else if styles_to_clear.contains(Style::INVERSE) {
-            piecemeal_gubbins.push("27");
-        }
-        if styles_to_set.contains(Style::ITALIC) {
-            piecemeal_gubbins.push("3");
-        } else if styles_to_clear.contains(Style::ITALIC) {
-            piecemeal_gubbins.push("23");
-        }
-        if fg != self.cur_fg {
-            piecemeal_gubbins.push(fg.map(|x| x.as_ansi_fg()).unwrap_or("39"));
-        }
-        if bg != self.cur_bg {
-            piecemeal_gubbins.push(fg.map(|x| x.as_ansi_bg()).unwrap_or("49"));
-        }
-        let piecemeal = gubbins_to_sequence(&piecemeal_gubbins);
-        let mut flockmeal_gubbins = Vec::with_capacity(8);
-        flockmeal_gubbins.push("0");
-        if style.contains(Style::BOLD) {
-            flockmeal_gubbins.push("1");
-        }
-        if style.contains(Style::DIM) {
-            flockmeal_gubbins.push("2");
-        }
-        if style.contains(Style::UNDERLINE) {
-            flockmeal_gubbins.push("4");
-        }
-        if style.contains(Style::INVERSE) {
-            flockmeal_gubbins.push("7");
-        }
-        if style.contains(Style::ITALIC) {
-            flockmeal_gubbins.push("3");
-        }
-        if let Some(fg) = fg {
-            flockmeal_gubbins.push(fg.as_ansi_fg());
-        }
-        if let Some(bg) = bg {
-            flockmeal_gubbins.push(bg.as_ansi_bg());
-        }
-        let flockmeal = gubbins_to_sequence(&flockmeal_gubbins);
-        if flockmeal.len() <= piecemeal.len() {
-            // flockmeal should win all else being equal, as it is slightly
-            // less likely to go wrong
-            self.stdout.write_all(flockmeal.as_bytes())?;
-        } else {
-            self.stdout.write_all(piecemeal.as_bytes())?;
-        }
+        let fg = fg.and_then(|x| x.downgrade(self.color_depth));
+        let bg = bg.and_then(|x| x.downgrade(self.color_depth));
+        let style = normalize_style(style);
+        let sequence = sgr_transition(
+            (self.cur_style, self.cur_fg, self.cur_bg),
+            (style, fg, bg),
+        );
+        self.stdout.write_all(sequence.as_bytes())?;
         self.cur_style = style;
         self.cur_fg = fg;
         self.cur_bg = bg;
         Ok(())
     }
     fn reset_attrs(&mut self) -> LifeOrDeath {
+        self.set_link(None)?;
         self.set_attrs(Style::PLAIN, None, None)
     }
+    fn set_link(&mut self, link: Option<&str>) -> LifeOrDeath {
+        let link = sanitize_link(link);
+        if link == self.cur_link.as_deref() {
+            return Ok(());
+        }
+        self.stdout.write_all(link_sequence(link).as_bytes())?;
+        self.cur_link = link.map(str::to_string);
+        Ok(())
+    }
     fn print(&mut self, text: &str) -> LifeOrDeath {
         self.stdout.write_all(text.as_bytes())?;
         Ok(())
@@ -366,7 +331,10 @@ impl Term for AnsiTerminal {
         Ok(())
     }
     fn get_width(&mut self) -> u32 {
-        termsize::get().map(|x| x.cols as u32).unwrap_or(80)
+        terminal_size().map(|x| x.0).unwrap_or(80)
+    }
+    fn get_height(&mut self) -> u32 {
+        terminal_size().map(|x| x.1).unwrap_or(24)
     }
     fn flush(&mut self) -> LifeOrDeath {
         self.stdout.flush()?;
@@ -374,12 +342,13 @@ impl Term for AnsiTerminal {
     }
     fn unsuspend(&mut self) -> LifeOrDeath {
         assert!(self.suspended);
+        resume_captures();
         // hide cursor, disable line wrap, reset style
         self.stdout.write_all(b"\x1B[25h\x1B[7l\x1B[0m")?;
         let old_hook = panic::take_hook();
         let default_hook = panic::take_hook();
         panic::set_hook(Box::new(move |info| {
-            let mut stdout = std::io::stdout();
+            let mut stdout = real_stdout();
             // show cursor, enable line wrap, reset style, clear forward
             let _ = stdout.write_all(b"\x1B[25l\x1B[7h\x1B[0m\x1B[J");
             let _ = stdout.flush();
@@ -394,6 +363,7 @@ impl Term for AnsiTerminal {
     }
     fn suspend(&mut self) -> LifeOrDeath {
         assert!(!self.suspended);
+        self.set_link(None)?;
         // show cursor, enable line wrap, reset style, clear forward
         self.stdout.write_all(b"\x1B[25l\x1B[7h\x1B[0m\x1B[J")?;
         crate::exit_raw_mode();
@@ -404,6 +374,7 @@ impl Term for AnsiTerminal {
         if let Some(old_hook) = self.old_hook.take() {
             panic::set_hook(old_hook);
         }
+        pause_captures();
         self.suspended = true;
         Ok(())
     }
@@ -414,24 +385,26 @@ impl Term for AnsiTerminal {
         self.input_thread.interrupt();
         Ok(())
     }
-}
-
-fn gubbins_to_sequence(gubbins: &[&str]) -> String {
-    if gubbins.is_empty() {
-        return String::new();
+    fn pause_input(&mut self) -> bool {
+        if !InterruptibleStdinThread::CAN_INTERRUPT {
+            return false;
+        }
+        self.input_thread.interrupt();
+        true
     }
-    let mut ret = String::with_capacity(
-        gubbins.iter().map(|x| x.len() + 1).sum::<usize>() + 2,
-    );
-    ret.push('\x1B');
-    for (i, gubbin) in gubbins.iter().enumerate() {
-        if i == 0 {
-            ret.push('[');
-        } else {
-            ret.push(';');
-        };
-        ret += gubbin;
+    fn resume_input(&mut self) {
+        self.input_thread =
+            spawn_stdin_thread(self.input_tx.clone(), self.cast.clone());
+    }
+    fn set_cast_recording(
+        &mut self,
+        recording: Option<CastRecording>,
+    ) -> Result<bool, DummyError> {
+        let size = (self.get_width(), self.get_height());
+        self.stdout.set_cast_recording(recording, size)?;
+        Ok(true)
+    }
+    fn take_cast_error(&mut self) -> Option<std::io::Error> {
+        self.cast.lock().unwrap().take_error()
     }
-    ret.push('m');
-    ret
 }
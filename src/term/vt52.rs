@@ -14,13 +14,17 @@ pub(crate) struct Vt52 {
     suspended: bool,
     old_hook:
         Option<Box<dyn Fn(&panic::PanicHookInfo<'_>) + Sync + Send + 'static>>,
-    stdout: Stdout,
+    stdout: RealStdout,
     num_colors: u8,
+    color_depth: ColorDepth,
     cur_style: Style,
     cur_fg: u8,
     cur_bg: u8,
     white_on_black: bool,
     input_thread: InterruptibleStdinThread,
+    /// Where the raw stdin thread sends what it reads, kept so that the
+    /// thread can be restarted after `pause_input`.
+    input_tx: std_mpsc::SyncSender<Vec<u8>>,
 }
 
 fn input_thread(
@@ -101,10 +105,37 @@ fn input_thread(
     }
 }
 
+/// Starts the thread that reads raw bytes from stdin.
+fn spawn_stdin_thread(
+    input_tx: std_mpsc::SyncSender<Vec<u8>>,
+) -> InterruptibleStdinThread {
+    let join_handle = std::thread::Builder::new()
+        .name("Liso raw stdin thread".to_owned())
+        .spawn(move || {
+            let stdin = std::io::stdin();
+            let mut stdin = stdin.lock();
+            let mut buf = [0u8; 256];
+            loop {
+                let amt = match stdin.read(&mut buf[..]) {
+                    // as though nothing happened
+                    Err(x) if x.kind() == ErrorKind::Interrupted => continue,
+                    Ok(0) | Err(_) => break,
+                    Ok(x) => x,
+                };
+                if input_tx.send(buf[..amt].to_owned()).is_err() {
+                    break;
+                }
+            }
+        })
+        .unwrap();
+    InterruptibleStdinThread::new(join_handle)
+}
+
 impl Vt52 {
     pub(crate) fn new(
         req_tx: std_mpsc::Sender<Request>,
         num_colors: u8,
+        color_depth: ColorDepth,
     ) -> Result<Vt52, DummyError> {
         let white_on_black = match std::env::var("ATARI_WHITE_ON_BLACK")
             .as_ref()
@@ -127,32 +158,13 @@ impl Vt52 {
         };
         let (input_tx, input_rx) = std_mpsc::sync_channel(1);
         std::thread::Builder::new()
-            .name("Liso raw stdin thread".to_owned())
-            .spawn(move || {
-                let stdin = std::io::stdin();
-                let mut stdin = stdin.lock();
-                let mut buf = [0u8; 256];
-                loop {
-                    let amt = match stdin.read(&mut buf[..]) {
-                        Err(x) if x.kind() == ErrorKind::Interrupted => {
-                            continue
-                        } // as though nothing happened
-                        Ok(0) | Err(_) => break,
-                        Ok(x) => x,
-                    };
-                    if input_tx.send(buf[..amt].to_owned()).is_err() {
-                        break;
-                    }
-                }
-            })
-            .unwrap();
-        let input_thread = std::thread::Builder::new()
             .name("Liso input processing thread".to_owned())
             .spawn(move || {
                 let _ = input_thread(input_rx, req_tx);
             })
             .unwrap();
-        let stdout = std::io::stdout();
+        let input_thread = spawn_stdin_thread(input_tx.clone());
+        let stdout = real_stdout();
         let mut ret = Vt52 {
             stdout,
             old_hook: None,
@@ -161,8 +173,16 @@ impl Vt52 {
             cur_fg: num_colors - 1,
             cur_bg: 0,
             num_colors,
+            // Our palettes are chosen from by named color, so that's the most
+            // we can do.
+            color_depth: color_depth.min(if num_colors > 2 {
+                ColorDepth::Ansi8
+            } else {
+                ColorDepth::Monochrome
+            }),
             white_on_black,
-            input_thread: InterruptibleStdinThread::new(input_thread),
+            input_thread,
+            input_tx,
         };
         ret.unsuspend()?;
         Ok(ret)
@@ -176,6 +196,8 @@ impl Term for Vt52 {
         fg: Option<Color>,
         bg: Option<Color>,
     ) -> LifeOrDeath {
+        let fg = fg.and_then(|x| x.downgrade(self.color_depth));
+        let bg = bg.and_then(|x| x.downgrade(self.color_depth));
         // the only styling supported by the Atari VT52 emulator was inverse
         // video, and we end up emulating it anyway to get our bright/dim split
         // working
@@ -211,6 +233,13 @@ impl Term for Vt52 {
         } else {
             (fg, bg)
         };
+        // none of the other styles can be done with colors alone, except
+        // conceal
+        let fg = if style.contains(Style::CONCEAL) {
+            bg
+        } else {
+            fg
+        };
         self.cur_style = style;
         if fg != self.cur_fg {
             self.cur_fg = fg;
@@ -235,6 +264,10 @@ impl Term for Vt52 {
         self.cur_style = Style::PLAIN;
         Ok(())
     }
+    fn set_link(&mut self, _link: Option<&str>) -> LifeOrDeath {
+        // No such thing on a VT52. The text alone will have to do.
+        Ok(())
+    }
     fn print(&mut self, text: &str) -> LifeOrDeath {
         // Atari ST doesn't support Unicode. In fact, its VT52 emulator doesn't
         // even support 8 bits. So we output a weird delta character any time
@@ -357,7 +390,10 @@ impl Term for Vt52 {
         Ok(())
     }
     fn get_width(&mut self) -> u32 {
-        termsize::get().map(|x| x.cols as u32).unwrap_or(80)
+        terminal_size().map(|x| x.0).unwrap_or(80)
+    }
+    fn get_height(&mut self) -> u32 {
+        terminal_size().map(|x| x.1).unwrap_or(24)
     }
     fn flush(&mut self) -> LifeOrDeath {
         self.stdout.flush()?;
@@ -365,6 +401,7 @@ impl Term for Vt52 {
     }
     fn unsuspend(&mut self) -> LifeOrDeath {
         assert!(self.suspended);
+        resume_captures();
         // queue, but don't actually output anything until the first command...
         if self.white_on_black {
             self.stdout
@@ -376,11 +413,10 @@ impl Term for Vt52 {
         let old_hook = panic::take_hook();
         let default_hook = panic::take_hook();
         panic::set_hook(Box::new(move |info| {
-            let mut stdout = std::io::stdout().lock();
+            let mut stdout = real_stdout();
             // fgcolor=15, bgcolor=0, clear from cursor, wrap on, enable cursor
-            let _ = write!(stdout, "\x1Bb\x2F\x1Bc\x20\x1BJ\x1Bv\x1Be");
+            let _ = stdout.write_all(b"\x1Bb\x2F\x1Bc\x20\x1BJ\x1Bv\x1Be");
             let _ = stdout.flush();
-            drop(stdout);
             crate::exit_raw_mode();
             default_hook(info)
         }));
@@ -403,6 +439,7 @@ impl Term for Vt52 {
             panic::set_hook(old_hook);
         }
         crate::exit_raw_mode();
+        pause_captures();
         self.suspended = true;
         Ok(())
     }
@@ -413,4 +450,14 @@ impl Term for Vt52 {
         self.input_thread.interrupt();
         Ok(())
     }
+    fn pause_input(&mut self) -> bool {
+        if !InterruptibleStdinThread::CAN_INTERRUPT {
+            return false;
+        }
+        self.input_thread.interrupt();
+        true
+    }
+    fn resume_input(&mut self) {
+        self.input_thread = spawn_stdin_thread(self.input_tx.clone());
+    }
 }
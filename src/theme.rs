@@ -0,0 +1,113 @@
+//! Named styles, so that the look of a program's output can be defined in one
+//! place and changed at runtime.
+
+use super::*;
+
+use std::collections::HashMap;
+
+/// One entry in a [`Theme`](struct.Theme.html): a [`Style`][1], plus
+/// optional foreground and background [`Color`][2]s.
+///
+/// [1]: struct.Style.html
+/// [2]: enum.Color.html
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThemeEntry {
+    /// Styles to add to the text.
+    pub style: Style,
+    /// Foreground color to use, unless the text has its own.
+    pub fg: Option<Color>,
+    /// Background color to use, unless the text has its own.
+    pub bg: Option<Color>,
+}
+
+/// A set of named styles. Rather than hard-coding `fg = red` everywhere you
+/// print an error, you can mark the text with `theme = "error"` (see
+/// [`Line::set_theme`][1]) and decide what errors look like in one place.
+/// Since names are looked up when a line is displayed, calling
+/// [`Output::set_theme`][2] (e.g. to switch between themes for light and
+/// dark terminals) will restyle the prompt and status line right away, as well
+/// as all future output.
+///
+/// The default theme has the following entries:
+///
+/// - `error`: bold red (also used for captured stderr's `E:` prefix)
+/// - `warning`: bold yellow
+/// - `prompt`: bold (used for the history search prompt)
+/// - `muted`: dim
+/// - `stderr`: red (used for the text of captured stderr)
+/// - `progress`: green (used for progress bars and spinners)
+/// - `info`: green (used for the level of `log` records)
+/// - `debug`: blue (used for the level of `log` records)
+/// - `trace`: dim (used for the level of `log` records)
+///
+/// ```rust
+/// # use liso::{liso, Color, Style, Theme};
+/// let mut theme = Theme::default();
+/// theme.set("error", Style::INVERSE, Some(Color::Red), None);
+/// theme.set("filename", Style::UNDERLINE, None, None);
+/// let line = liso!(theme = "error", "E:", theme = "filename", " x.rs");
+/// assert_eq!(line.resolve_theme(&theme),
+///            liso!(inverse, fg = red, "E:", underline, fg = none, " x.rs"));
+/// ```
+///
+/// [1]: struct.Line.html#method.set_theme
+/// [2]: struct.Output.html#method.set_theme
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    entries: HashMap<String, ThemeEntry>,
+}
+
+impl Theme {
+    /// Creates a theme with no entries at all.
+    pub fn new() -> Theme {
+        Theme {
+            entries: HashMap::new(),
+        }
+    }
+    /// Adds an entry to the theme, replacing any existing entry with the same
+    /// name.
+    pub fn set<T>(
+        &mut self,
+        name: T,
+        style: Style,
+        fg: Option<Color>,
+        bg: Option<Color>,
+    ) -> &mut Theme
+    where
+        T: Into<String>,
+    {
+        self.entries
+            .insert(name.into(), ThemeEntry { style, fg, bg });
+        self
+    }
+    /// Gets the entry with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<ThemeEntry> {
+        self.entries.get(name).copied()
+    }
+    /// Removes the entry with the given name, returning it if there was one.
+    pub fn remove(&mut self, name: &str) -> Option<ThemeEntry> {
+        self.entries.remove(name)
+    }
+    /// Iterates over the entries in the theme, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, ThemeEntry)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let mut ret = Theme::new();
+        ret.set("error", Style::BOLD, Some(Color::Red), None)
+            .set("warning", Style::BOLD, Some(Color::Yellow), None)
+            .set("prompt", Style::BOLD, None, None)
+            .set("muted", Style::DIM, None, None)
+            .set("stderr", Style::PLAIN, Some(Color::Red), None)
+            .set("progress", Style::PLAIN, Some(Color::Green), None)
+            .set("info", Style::PLAIN, Some(Color::Green), None)
+            .set("debug", Style::PLAIN, Some(Color::Blue), None)
+            .set("trace", Style::DIM, None, None);
+        ret
+    }
+}
@@ -0,0 +1,276 @@
+//! A `tracing-subscriber` layer, so that tracing events don't scramble the
+//! prompt.
+
+use super::*;
+
+use std::{fmt::Debug, fmt::Write, time::SystemTime};
+
+use tracing_core::{
+    field::{Field, Visit},
+    span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// A [`tracing-subscriber`](https://docs.rs/tracing-subscriber) `Layer` that
+/// prints events through Liso, as styled output. Requires the `tracing`
+/// feature.
+///
+/// Each event looks like this:
+///
+/// ```text
+/// [2026-01-02T03:04:05.678Z  INFO my_program::net] connect: Connected port=80
+/// ```
+///
+/// where `connect` is the span the event happened in (if any), followed by
+/// the event's message and its other fields. The level is styled the same
+/// way as a [`Logger`](struct.Logger.html)'s would be: using the theme entry
+/// named after it (`error`, `warning`, `info`, `debug`, or `trace`). The
+/// timestamp, target, and span names are styled with `muted`. (See
+/// [`Theme`](struct.Theme.html).) Timestamps are in UTC.
+///
+/// This layer doesn't filter anything. Combine it with the filters in
+/// `tracing-subscriber` to choose which events are shown.
+///
+/// ```rust,no_run
+/// use tracing_subscriber::prelude::*;
+/// # let io = liso::InputOutput::new();
+/// tracing_subscriber::registry()
+///     .with(liso::TracingLayer::new(&io)
+///               .status_slot(Some("tracing"))
+///               .with_filter(tracing_subscriber::filter::LevelFilter::INFO))
+///     .init();
+/// ```
+///
+/// Events and span changes that happen after Liso has shut down are ignored.
+pub struct TracingLayer {
+    output: OutputOnly,
+    show_target: bool,
+    show_timestamp: bool,
+    show_spans: bool,
+    status_slot: Option<String>,
+}
+
+impl TracingLayer {
+    /// Creates a layer that prints through the given `Output`. By default,
+    /// it shows every event with its target and a timestamp, and nothing
+    /// else.
+    pub fn new(output: &Output) -> TracingLayer {
+        TracingLayer {
+            output: output.clone_output(),
+            show_target: true,
+            show_timestamp: true,
+            show_spans: false,
+            status_slot: None,
+        }
+    }
+    /// Sets whether each event's target (usually the module it came from)
+    /// is shown.
+    pub fn show_target(mut self, show_target: bool) -> TracingLayer {
+        self.show_target = show_target;
+        self
+    }
+    /// Sets whether each event is shown with a timestamp.
+    pub fn show_timestamp(mut self, show_timestamp: bool) -> TracingLayer {
+        self.show_timestamp = show_timestamp;
+        self
+    }
+    /// Sets whether a line is printed whenever a span is entered or exited,
+    /// at the span's level.
+    pub fn show_spans(mut self, show_spans: bool) -> TracingLayer {
+        self.show_spans = show_spans;
+        self
+    }
+    /// Sets a status slot that will show the spans that were most recently
+    /// entered (from outermost to innermost), which is to say, what the
+    /// program is doing at the moment. The slot is removed when there are no
+    /// spans. If several threads are in spans at once, the slot shows
+    /// whichever thread entered or exited a span last.
+    pub fn status_slot<K>(mut self, key: Option<K>) -> TracingLayer
+    where
+        K: Into<String>,
+    {
+        self.status_slot = key.map(K::into);
+        self
+    }
+    fn send(&self, request: Request) {
+        // (ignore failure, the same as `Logger`)
+        let _ = self.output.0.tx.send(request);
+    }
+    /// Starts a line the way every line we print starts: with the timestamp,
+    /// level, and target, in brackets.
+    fn header(&self, time: SystemTime, level: &Level, target: &str) -> Line {
+        let mut ret = Line::new();
+        liso_add!(ret, "[");
+        if self.show_timestamp {
+            liso_add!(
+                ret,
+                theme = "muted",
+                format_timestamp(time),
+                reset,
+                " "
+            );
+        }
+        let (theme, name) = match *level {
+            Level::ERROR => ("error", "ERROR"),
+            Level::WARN => ("warning", " WARN"),
+            Level::INFO => ("info", " INFO"),
+            Level::DEBUG => ("debug", "DEBUG"),
+            Level::TRACE => ("trace", "TRACE"),
+        };
+        liso_add!(ret, theme = theme, name, reset);
+        if self.show_target {
+            liso_add!(ret, " ", theme = "muted", target, reset);
+        }
+        liso_add!(ret, "] ");
+        ret
+    }
+    /// Formats an event the way it will be printed. `spans` are the names of
+    /// the spans the event happened in, outermost first.
+    fn format_event(
+        &self,
+        time: SystemTime,
+        event: &Event,
+        spans: &[&str],
+    ) -> Line {
+        let metadata = event.metadata();
+        let mut ret = self.header(time, metadata.level(), metadata.target());
+        if !spans.is_empty() {
+            liso_add!(ret, theme = "muted", spans.join(":"), reset, ": ");
+        }
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        ret.add_text(fields.text);
+        ret
+    }
+    fn span_changed<S>(&self, id: &span::Id, ctx: Context<'_, S>, verb: &str)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if self.show_spans {
+            if let Some(span) = ctx.span(id) {
+                let metadata = span.metadata();
+                let mut line = self.header(
+                    SystemTime::now(),
+                    metadata.level(),
+                    metadata.target(),
+                );
+                liso_add!(line, verb, " ", theme = "muted", span.name());
+                self.send(Request::Output(line));
+            }
+        }
+        if let Some(key) = self.status_slot.as_ref() {
+            let spans: Vec<&str> = ctx
+                .lookup_current()
+                .map(|x| x.scope().from_root().map(|x| x.name()).collect())
+                .unwrap_or_default();
+            if spans.is_empty() {
+                self.send(Request::RemoveStatusSlot(key.clone()));
+            } else {
+                self.send(Request::StatusSlot(
+                    key.clone(),
+                    liso!(theme = "muted", spans.join(" > ")),
+                ));
+            }
+        }
+    }
+}
+
+impl<S> Layer<S> for TracingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let spans: Vec<&str> = ctx
+            .event_scope(event)
+            .map(|x| x.from_root().map(|x| x.name()).collect())
+            .unwrap_or_default();
+        self.send(Request::Output(self.format_event(
+            SystemTime::now(),
+            event,
+            &spans,
+        )));
+    }
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.span_changed(id, ctx, "enter");
+    }
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.span_changed(id, ctx, "exit");
+    }
+}
+
+/// Turns an event's fields into text: the message, then `name=value` for
+/// every other field.
+#[derive(Default)]
+struct FieldVisitor {
+    text: String,
+}
+
+impl FieldVisitor {
+    fn separate(&mut self) {
+        if !self.text.is_empty() {
+            self.text.push(' ');
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.separate();
+            self.text.push_str(value);
+        } else {
+            self.record_debug(field, &value)
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.separate();
+        if field.name() == "message" {
+            let _ = write!(self.text, "{:?}", value);
+        } else {
+            let _ = write!(self.text, "{}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+    #[test]
+    fn tracing_layer() {
+        let (output, rx) = Output::for_test();
+        let layer = TracingLayer::new(&output)
+            .show_timestamp(false)
+            .status_slot(Some("tracing"));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _outer = tracing::info_span!("outer").entered();
+            let _inner = tracing::info_span!("inner").entered();
+            tracing::warn!(target: "app", port = 80, "Connected to {}", "x");
+        });
+        let lines: Vec<Line> = rx
+            .try_iter()
+            .map(|request| match request {
+                Request::Output(line) => line,
+                Request::StatusSlot(key, line) => {
+                    assert_eq!(key, "tracing");
+                    line
+                }
+                Request::RemoveStatusSlot(_) => Line::new(),
+                _ => panic!("unexpected request"),
+            })
+            .map(|line| Line::from(line.as_str().to_string()))
+            .collect();
+        let expected: Vec<Line> = [
+            "outer",
+            "outer > inner",
+            "[ WARN app] outer:inner: Connected to x port=80",
+            "outer",
+            "",
+        ]
+        .iter()
+        .map(|x| Line::from(*x))
+        .collect();
+        assert_eq!(lines, expected);
+    }
+}
@@ -0,0 +1,283 @@
+//! Recording everything that goes on in a session to a file.
+
+use super::*;
+
+use std::{
+    fs::OpenOptions,
+    io,
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How a [`Transcript`](struct.Transcript.html) is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TranscriptFormat {
+    /// One line of text per line of the session, each starting with a
+    /// timestamp and the kind of entry. All styling is discarded.
+    ///
+    /// ```text
+    /// 2026-01-02T03:04:05.678Z output: Hello World!
+    /// ```
+    Plain,
+    /// The same as `Plain`, except that styling is kept, in the form of ANSI
+    /// escape sequences. (See
+    /// [`Line::to_ansi_string`](struct.Line.html#method.to_ansi_string).)
+    /// Good for viewing with `less -R`.
+    Ansi,
+    /// One JSON object per entry, each on its own line, with `time`, `kind`,
+    /// and `text` fields. All styling is discarded.
+    ///
+    /// ```text
+    /// {"time":"2026-01-02T03:04:05.678Z","kind":"output","text":"Hello World!"}
+    /// ```
+    JsonLines,
+}
+
+/// What kind of thing an entry in a transcript is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TranscriptKind {
+    /// Printed with `println`, `wrapln`, etc.
+    Output,
+    /// Printed with `echoln`.
+    Echo,
+    /// A line of input from the user.
+    Input,
+    /// Displayed with `notice`.
+    Notice,
+    /// Captured from stderr.
+    #[cfg_attr(not(feature = "capture-stderr"), allow(dead_code))]
+    Stderr,
+}
+
+impl TranscriptKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TranscriptKind::Output => "output",
+            TranscriptKind::Echo => "echo",
+            TranscriptKind::Input => "input",
+            TranscriptKind::Notice => "notice",
+            TranscriptKind::Stderr => "stderr",
+        }
+    }
+}
+
+/// A record of a session: every line of output, echoed input, line of input,
+/// notice, and captured line of stderr, each with a timestamp. Install one
+/// with [`Output::set_transcript`][1].
+///
+/// Timestamps are in UTC, in RFC 3339 format. Themed text is recorded the way
+/// it looked when it was displayed. Each entry is flushed as soon as it's
+/// written.
+///
+/// If writing to the transcript ever fails, Liso prints an error and stops
+/// recording.
+///
+/// ```rust,no_run
+/// # use liso::{Transcript, TranscriptFormat};
+/// # let io = liso::InputOutput::new();
+/// io.set_transcript(Some(Transcript::from_file("console.log",
+///                                              TranscriptFormat::Plain)
+///                        .unwrap()));
+/// ```
+///
+/// [1]: struct.Output.html#method.set_transcript
+pub struct Transcript {
+    writer: Box<dyn Write + Send>,
+    format: TranscriptFormat,
+}
+
+impl Transcript {
+    /// Creates a transcript that writes to the given writer in the given
+    /// format.
+    pub fn new<W>(writer: W, format: TranscriptFormat) -> Transcript
+    where
+        W: Write + Send + 'static,
+    {
+        Transcript {
+            writer: Box::new(writer),
+            format,
+        }
+    }
+    /// Creates a transcript that writes to the given file in the given
+    /// format. If the file already exists, the transcript is added to the
+    /// end of it.
+    pub fn from_file<P: AsRef<Path>>(
+        path: P,
+        format: TranscriptFormat,
+    ) -> io::Result<Transcript> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Transcript::new(file, format))
+    }
+    pub(crate) fn record(
+        &mut self,
+        kind: TranscriptKind,
+        line: &Line,
+    ) -> io::Result<()> {
+        self.record_at(SystemTime::now(), kind, line)
+    }
+    fn record_at(
+        &mut self,
+        time: SystemTime,
+        kind: TranscriptKind,
+        line: &Line,
+    ) -> io::Result<()> {
+        let time = format_timestamp(time);
+        match self.format {
+            TranscriptFormat::Plain | TranscriptFormat::Ansi => {
+                let rows: Vec<Line> = if line.is_empty() {
+                    vec![Line::new()]
+                } else {
+                    line.lines().collect()
+                };
+                for row in rows.iter() {
+                    let text = match self.format {
+                        TranscriptFormat::Ansi => {
+                            Cow::Owned(row.to_ansi_string())
+                        }
+                        _ => Cow::Borrowed(row.as_str()),
+                    };
+                    writeln!(
+                        self.writer,
+                        "{} {}: {}",
+                        time,
+                        kind.as_str(),
+                        text
+                    )?;
+                }
+            }
+            TranscriptFormat::JsonLines => {
+                writeln!(
+                    self.writer,
+                    "{{\"time\":\"{}\",\"kind\":\"{}\",\"text\":\"{}\"}}",
+                    time,
+                    kind.as_str(),
+                    json_escape(line.as_str())
+                )?;
+            }
+        }
+        self.writer.flush()
+    }
+}
+
+/// Formats the given time as an RFC 3339 timestamp in UTC, with
+/// milliseconds.
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Converts a number of days since 1970-01-01 into a (proleptic Gregorian)
+/// year, month, and day. This is Howard Hinnant's `civil_from_days`
+/// algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Escapes text for use inside a JSON string.
+pub(crate) fn json_escape(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(|ch| ch < ' ' || ch == '"' || ch == '\\') {
+        return Cow::Borrowed(text);
+    }
+    let mut ret = String::with_capacity(text.len() + 8);
+    for ch in text.chars() {
+        match ch {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            ch if ch < ' ' => ret.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => ret.push(ch),
+        }
+    }
+    Cow::Owned(ret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+    /// A writer whose output we can look at afterward.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    #[test]
+    fn transcript_formats() {
+        // 2001-09-09T01:46:40.250Z
+        let time = UNIX_EPOCH + Duration::from_millis(1_000_000_000_250);
+        let line = liso!("Hello ", bold, "World\n", plain, "\"quoted\"");
+        let expected = [
+            (
+                TranscriptFormat::Plain,
+                "2001-09-09T01:46:40.250Z output: Hello World\n\
+                 2001-09-09T01:46:40.250Z output: \"quoted\"\n",
+            ),
+            (
+                TranscriptFormat::Ansi,
+                "2001-09-09T01:46:40.250Z output: Hello \x1B[1mWorld\x1B[0m\n\
+                 2001-09-09T01:46:40.250Z output: \"quoted\"\n",
+            ),
+            (
+                TranscriptFormat::JsonLines,
+                "{\"time\":\"2001-09-09T01:46:40.250Z\",\"kind\":\"output\",\
+                 \"text\":\"Hello World\\n\\\"quoted\\\"\"}\n",
+            ),
+        ];
+        for (format, expected) in expected.into_iter() {
+            let buf = SharedBuf::default();
+            let mut transcript = Transcript::new(buf.clone(), format);
+            transcript
+                .record_at(time, TranscriptKind::Output, &line)
+                .unwrap();
+            let output = String::from_utf8(buf.0.lock().unwrap().clone());
+            assert_eq!(output.unwrap(), expected);
+        }
+    }
+    #[test]
+    fn transcript_timestamps() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(format_timestamp(leap_day), "2000-02-29T00:00:00.000Z");
+        let new_years_eve = UNIX_EPOCH + Duration::from_secs(1_735_689_599);
+        assert_eq!(
+            format_timestamp(new_years_eve),
+            "2024-12-31T23:59:59.000Z"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Output lines that can be changed after they've been printed.
+
+use super::*;
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+/// Used to give every `UpdatableLine` a unique identity.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A handle to a line of output that can be changed after it's printed.
+/// Create one with [`Output::println_updatable`][1].
+///
+/// While the line is still on the screen, changing it rewrites it in place.
+/// (If the new text takes up a different number of rows, the output below it
+/// is moved to make room.) Once the line has scrolled out of reach, or if
+/// something happened that Liso can't keep track of (such as the screen being
+/// cleared, or a call to [`suspend_and_run`][2]), changing it prints the new
+/// text as a fresh line instead, which can then be changed in turn. In pipe
+/// mode, every change prints a fresh line.
+///
+/// ```rust,no_run
+/// # use liso::liso;
+/// # let io = liso::InputOutput::new();
+/// let line = io.println_updatable("Downloading foo... ");
+/// // ... download foo ...
+/// line.append(liso!(fg = green, "done"));
+/// ```
+///
+/// Handles can be cloned and sent to other threads. All clones refer to the
+/// same line.
+///
+/// [1]: struct.Output.html#method.println_updatable
+/// [2]: struct.Output.html#method.suspend_and_run
+#[derive(Clone)]
+pub struct UpdatableLine {
+    id: u64,
+    /// What the line currently says.
+    line: Arc<Mutex<Line>>,
+    output: OutputOnly,
+}
+
+impl UpdatableLine {
+    pub(crate) fn new(output: OutputOnly, line: Line) -> UpdatableLine {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        output.send(Request::OutputUpdatable(id, line.clone()));
+        UpdatableLine {
+            id,
+            line: Arc::new(Mutex::new(line)),
+            output,
+        }
+    }
+    /// Returns a copy of what the line currently says.
+    pub fn get(&self) -> Line {
+        self.line.lock().unwrap().clone()
+    }
+    /// Replaces the (possibly styled) text of the line.
+    pub fn replace<T>(&self, line: T)
+    where
+        T: Into<Line>,
+    {
+        let line = line.into();
+        let mut lock = self.line.lock().unwrap();
+        *lock = line.clone();
+        // (send while still locked, so that simultaneous changes from
+        // different threads arrive in the same order they were made)
+        self.output.send(Request::OutputUpdatable(self.id, line));
+    }
+    /// Adds some (possibly styled) text to the end of the line, the same way
+    /// as [`Line::append_line`](struct.Line.html#method.append_line).
+    pub fn append<T>(&self, line: T)
+    where
+        T: Into<Line>,
+    {
+        let mut lock = self.line.lock().unwrap();
+        lock.append_line(&line.into());
+        self.output
+            .send(Request::OutputUpdatable(self.id, lock.clone()));
+    }
+}
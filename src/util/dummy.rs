@@ -1,6 +1,8 @@
 pub struct InterruptibleStdinThread;
 
 impl InterruptibleStdinThread {
+    /// Whether `interrupt` actually stops the thread on this platform.
+    pub const CAN_INTERRUPT: bool = false;
     pub fn new(
         _join_handle: std::thread::JoinHandle<()>,
     ) -> InterruptibleStdinThread {
@@ -42,6 +42,8 @@ pub struct InterruptibleStdinThread {
 extern "C" fn dummy_handler(_: i32) {}
 
 impl InterruptibleStdinThread {
+    /// Whether `interrupt` actually stops the thread on this platform.
+    pub const CAN_INTERRUPT: bool = true;
     pub fn new(join_handle: JoinHandle<()>) -> InterruptibleStdinThread {
         InterruptibleStdinThread {
             join_handle: Some(join_handle),
@@ -99,7 +101,12 @@ impl InterruptibleStdinThread {
 /// without raw mode being disabled in between. Return true if the input is a
 /// tty and raw input is possible.
 pub fn enter_raw_mode() -> bool {
-    if isatty(0) != Ok(true) || isatty(1) != Ok(true) {
+    // (if stdout is being captured, what matters is where it really goes)
+    #[cfg(feature = "capture-stdout")]
+    let stdout_fd = crate::capture::real_stdout_fd();
+    #[cfg(not(feature = "capture-stdout"))]
+    let stdout_fd = 1;
+    if isatty(0) != Ok(true) || isatty(stdout_fd) != Ok(true) {
         return false;
     }
     #[cfg(debug_assertions)]
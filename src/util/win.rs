@@ -20,6 +20,8 @@ pub(crate) struct InterruptibleStdinThread {
 extern "system" fn dummy_handler(_: usize) {}
 
 impl InterruptibleStdinThread {
+    /// Whether `interrupt` actually stops the thread on this platform.
+    pub const CAN_INTERRUPT: bool = true;
     pub fn new(join_handle: JoinHandle<()>) -> InterruptibleStdinThread {
         InterruptibleStdinThread {
             join_handle: Some(join_handle),
@@ -6,13 +6,36 @@ use super::*;
 
 use std::{
     cell::{RefCell, RefMut},
+    collections::VecDeque,
     io::BufRead,
     mem::swap,
+    rc::Rc,
     time::Instant,
 };
 
 use unicode_width::UnicodeWidthChar;
 
+/// How many lines of output we keep for review, unless told otherwise.
+const DEFAULT_SCROLLBACK_LIMIT: usize = 1000;
+
+/// Records a line in the transcript, if there is one. If that fails, stops
+/// recording, and returns an error message to display.
+fn record_transcript(
+    transcript: &mut Option<Transcript>,
+    kind: TranscriptKind,
+    line: &Line,
+) -> Option<Line> {
+    if let Some(x) = transcript.as_mut() {
+        if let Err(e) = x.record(kind, line) {
+            *transcript = None;
+            // TODO: make localizable
+            let e = format!("Unable to write transcript: {}", e);
+            return Some(liso!(theme = "error", e));
+        }
+    }
+    None
+}
+
 /// This is the actual worker used when we're in "pipe mode". That means we
 /// either have a dumb terminal or a piped stdin/stdout.
 fn pipe_worker(
@@ -43,26 +66,51 @@ fn pipe_worker(
             })
             .unwrap(),
     );
+    let mut transcript = None;
+    let mut theme = Theme::default();
+    let record = |transcript: &mut Option<Transcript>,
+                  theme: &Theme,
+                  kind: TranscriptKind,
+                  line: &Line| {
+        if let Some(error) =
+            record_transcript(transcript, kind, &line.themed(theme))
+        {
+            eprintln!("{}", error.text);
+        }
+    };
     while let Ok(request) = rx.recv() {
         match request {
-            #[cfg(feature = "wrap")]
-            Request::Output(line) | Request::OutputWrapped(line) => {
+            Request::Output(line) | Request::OutputUpdatable(_, line) => {
                 std::println!("{}", line.text);
+                record(&mut transcript, &theme, TranscriptKind::Output, &line);
             }
-            #[cfg(not(feature = "wrap"))]
-            Request::Output(line) => {
+            #[cfg(feature = "wrap")]
+            Request::OutputWrapped(mut line, options) => {
+                record(&mut transcript, &theme, TranscriptKind::Output, &line);
+                // no width to wrap to, but the indentation still applies
+                line.wrap_to_width_with(usize::MAX, &options);
                 std::println!("{}", line.text);
             }
             // stderr will not be captured if the pipe worker is being used.
             #[cfg(feature = "capture-stderr")]
             Request::StderrLine(_) => unreachable!(),
             Request::RawInput(x) => {
+                record(
+                    &mut transcript,
+                    &theme,
+                    TranscriptKind::Input,
+                    &Line::from_str(&x),
+                );
                 if tx.send(Response::Input(x)).is_err() {
                     break;
                 }
             }
             Request::Die => break,
             Request::Custom(x) => tx.send(Response::Custom(x))?,
+            Request::SetTheme(x) => theme = x,
+            Request::SetTranscript(x) => transcript = x,
+            // nothing to suspend, but it should still run
+            Request::SuspendAndRun(wat) => wat(),
             _ => (),
         }
     }
@@ -70,6 +118,40 @@ fn pipe_worker(
     Ok(())
 }
 
+/// The state of an incremental search through history.
+#[cfg(feature = "history")]
+struct HistorySearch {
+    query: String,
+    /// The index of the line that matched most recently, if any.
+    found: Option<usize>,
+    /// Whether the most recent attempt to find a match failed.
+    failing: bool,
+    /// The input from before the search, to put back if it's abandoned.
+    original_input: String,
+    original_cursor: usize,
+}
+
+#[cfg(feature = "history")]
+impl HistorySearch {
+    /// What to show in place of the prompt.
+    fn prompt(&self) -> Line {
+        // TODO: make localizable
+        let label = if self.failing {
+            "(failed reverse-i-search)"
+        } else {
+            "(reverse-i-search)"
+        };
+        liso!(
+            theme = "prompt",
+            label,
+            reset,
+            "`",
+            self.query.as_str(),
+            "': "
+        )
+    }
+}
+
 #[derive(Debug)]
 struct RememberedOutput {
     output_line: Line,
@@ -78,15 +160,50 @@ struct RememberedOutput {
     cursor_left: u32,
 }
 
+/// A line of output that might still be on the screen.
+#[derive(Debug)]
+struct RecentOutput {
+    /// `Some` if this line was printed by an `UpdatableLine`.
+    id: Option<u64>,
+    /// The line, with its theme already resolved.
+    line: Line,
+    /// How many rows of the terminal it took up.
+    rows: u32,
+}
+
 struct TtyState {
     status: Option<Line>,
+    status_area: StatusArea,
     prompt: Option<Line>,
     notice: Option<(Line, Instant)>,
+    /// When the next heartbeat for animating spinners is due, if one has been
+    /// arranged.
+    spinner_tick: Option<Instant>,
     input: String,
     clipboard: String,
     input_cursor: usize,
     input_allowed: bool,
     remembered_output: Option<RememberedOutput>,
+    /// How many rows the most recent rollout took up.
+    rollout_rows: u32,
+    /// Output lines that might still be on the screen, oldest first, so that
+    /// `UpdatableLine`s can be rewritten in place. Only goes back as far as
+    /// the oldest `UpdatableLine` that is still within reach.
+    recent_output: VecDeque<RecentOutput>,
+    /// The terminal width that everything in `recent_output` was printed at.
+    recent_output_width: u32,
+    /// Output lines we can page back through, oldest first. Each one is
+    /// tagged with the ID of its `UpdatableLine`, if it has one.
+    scrollback: VecDeque<(Option<u64>, Line)>,
+    scrollback_limit: usize,
+    /// The scrollback split into rows, and the width it was split for, so
+    /// that we don't have to do it again for every page of review. Cleared
+    /// whenever the scrollback changes.
+    scrollback_rows: RefCell<Option<(usize, Rc<Vec<Line>>)>>,
+    /// `Some` if we're reviewing the scrollback, in which case it's how many
+    /// rows back from the end of the scrollback we've gone.
+    review: Option<usize>,
+    transcript: Option<Transcript>,
     rollout_needed: bool,
     term: RefCell<Box<dyn Term>>,
     #[cfg(feature = "completion")]
@@ -105,20 +222,47 @@ struct TtyState {
     /// our place again.)
     #[cfg(feature = "history")]
     history_original_line: Option<String>,
+    /// The theme used to resolve themed text when it's displayed.
+    theme: Theme,
     #[cfg(feature = "completion")]
     completor: Option<Box<dyn Completor>>,
     #[cfg(feature = "completion")]
     consecutive_completion_presses: u32,
+    /// How to display captured stderr, if not the default way.
+    #[cfg(feature = "capture-stderr")]
+    stderr_decorator: Option<Box<dyn Fn(String) -> Option<Line> + Send>>,
+    /// Whether to send captured stderr to the program instead of displaying
+    /// it.
+    #[cfg(feature = "capture-stderr")]
+    stderr_responses: bool,
+    /// What each key does.
+    keymap: Keymap,
+    /// Keys that have been pressed so far, that might be the start of a
+    /// chord.
+    pending_keys: Vec<Key>,
+    /// If `pending_keys` isn't empty, when we'll stop waiting for the rest
+    /// of the chord.
+    chord_deadline: Option<Instant>,
+    /// `Some` if we're searching through history.
+    #[cfg(feature = "history")]
+    history_search: Option<HistorySearch>,
+    /// Whether input edited in an external editor is sent as soon as the
+    /// editor exits.
+    editor_submits: bool,
 }
 
 impl TtyState {
-    /// Output a Line, followed by a single linebreak.
-    fn output_line(&self, line: &Line) -> LifeOrDeath {
+    /// Output a Line, followed by a single linebreak. Returns the number of
+    /// rows it took up.
+    fn output_line(&self, line: &Line) -> Result<u32, DummyError> {
+        let line = line.themed(&self.theme);
         let mut term = self.term.borrow_mut();
         let term_width = term.get_width();
         let mut cur_column = 0;
+        let mut rows = 1;
         for element in line.elements.iter() {
             term.set_attrs(element.style, element.fg, element.bg)?;
+            term.set_link(element.link.as_deref())?;
             let text = &line.text[element.start..element.end];
             let mut cur = 0;
             for (idx, ch) in text.char_indices() {
@@ -134,7 +278,7 @@ impl TtyState {
                     }
                     if cur_column < term_width {
                         if term.cur_style().contains(Style::INVERSE)
-                            || term.cur_style().contains(Style::UNDERLINE)
+                            || term.cur_style().intersects(Style::LINES)
                             || element.bg.is_some()
                         {
                             term.print_spaces(
@@ -145,6 +289,7 @@ impl TtyState {
                         }
                     }
                     term.newline()?;
+                    rows += 1;
                     cur_column = 0;
                 }
                 cur_column += char_width;
@@ -157,10 +302,11 @@ impl TtyState {
             None => false,
             Some(el) => {
                 el.style.contains(Style::INVERSE)
-                    || el.style.contains(Style::UNDERLINE)
+                    || el.style.intersects(Style::LINES)
                     || el.bg.is_some()
             }
         };
+        term.set_link(None)?;
         if trailit && cur_column < term_width {
             term.print_spaces((term_width - cur_column) as usize)?;
         }
@@ -169,8 +315,124 @@ impl TtyState {
             term.clear_to_end_of_line()?;
         }
         term.newline()?;
+        Ok(rows)
+    }
+    /// Output a Line, followed by a single linebreak, and remember it in case
+    /// it has to be rewritten later. (`id` is `Some` if it's the line of an
+    /// `UpdatableLine`.)
+    ///
+    /// Also adds it to the scrollback. If we're reviewing the scrollback,
+    /// that's *all* it does; it'll be on the screen when review ends.
+    fn output_recent_line(
+        &mut self,
+        id: Option<u64>,
+        line: &Line,
+    ) -> LifeOrDeath {
+        let line = line.themed(&self.theme).into_owned();
+        self.add_to_scrollback(id, line.clone());
+        if self.review.is_some() {
+            return Ok(());
+        }
+        self.rollin()?;
+        let rows = self.output_line(&line)?;
+        self.term.borrow_mut().reset_attrs()?;
+        self.check_recent_output_width();
+        if id.is_some() || !self.recent_output.is_empty() {
+            self.recent_output
+                .push_back(RecentOutput { id, line, rows });
+            self.trim_recent_output();
+        }
         Ok(())
     }
+    fn add_to_scrollback(&mut self, id: Option<u64>, line: Line) {
+        if self.scrollback_limit == 0 {
+            return;
+        }
+        while self.scrollback.len() >= self.scrollback_limit {
+            self.scrollback.pop_front();
+        }
+        self.scrollback.push_back((id, line));
+        *self.scrollback_rows.get_mut() = None;
+    }
+    /// Replace the most recent scrollback line belonging to the given
+    /// `UpdatableLine`. Returns false if it isn't there.
+    fn update_scrollback(&mut self, id: u64, line: &Line) -> bool {
+        match self.scrollback.iter_mut().rev().find(|x| x.0 == Some(id)) {
+            Some(entry) => {
+                entry.1 = line.clone();
+                *self.scrollback_rows.get_mut() = None;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Forget all recent output if the terminal's width has changed, since we
+    /// no longer know how it's laid out.
+    fn check_recent_output_width(&mut self) {
+        let width = self.term.borrow_mut().get_width();
+        if width != self.recent_output_width {
+            self.recent_output.clear();
+            self.recent_output_width = width;
+        }
+    }
+    /// Forget recent output that we don't need to remember anymore: output
+    /// from before the oldest `UpdatableLine`, and anything that has scrolled
+    /// out of reach.
+    fn trim_recent_output(&mut self) {
+        let height = self.term.borrow_mut().get_height();
+        let mut total_rows: u32 =
+            self.recent_output.iter().map(|x| x.rows).sum();
+        while let Some(oldest) = self.recent_output.front() {
+            if oldest.id.is_some() && total_rows + self.rollout_rows <= height
+            {
+                break;
+            }
+            total_rows -= oldest.rows;
+            self.recent_output.pop_front();
+        }
+    }
+    /// Change the line of an `UpdatableLine`. If it's still on the screen,
+    /// rewrite it (and everything below it) in place. Otherwise, print it
+    /// anew.
+    fn update_output(&mut self, id: u64, line: Line) -> LifeOrDeath {
+        if self.review.is_some() {
+            let line = line.themed(&self.theme).into_owned();
+            if !self.update_scrollback(id, &line) {
+                self.add_to_scrollback(Some(id), line);
+            }
+            return Ok(());
+        }
+        self.rollin()?;
+        self.check_recent_output_width();
+        if let Some(index) =
+            self.recent_output.iter().position(|x| x.id == Some(id))
+        {
+            let height = self.term.borrow_mut().get_height();
+            let distance: u32 =
+                self.recent_output.range(index..).map(|x| x.rows).sum();
+            if distance + self.rollout_rows <= height {
+                {
+                    let mut term = self.term.borrow_mut();
+                    term.move_cursor_up(distance)?;
+                    term.clear_forward_and_reset()?;
+                }
+                let line = line.themed(&self.theme).into_owned();
+                self.update_scrollback(id, &line);
+                self.recent_output[index].line = line;
+                for index in index..self.recent_output.len() {
+                    let rows =
+                        self.output_line(&self.recent_output[index].line)?;
+                    self.recent_output[index].rows = rows;
+                }
+                self.term.borrow_mut().reset_attrs()?;
+                self.trim_recent_output();
+                return Ok(());
+            }
+            // (it's out of reach)
+            self.recent_output.remove(index);
+        }
+        self.output_recent_line(Some(id), &line)
+    }
     #[allow(clippy::too_many_arguments)]
     fn maybe_report(
         &self,
@@ -234,6 +496,7 @@ impl TtyState {
         term_width: u32,
         cur_attr: &mut (Style, Option<Color>, Option<Color>),
         lc: LineChar,
+        link: Option<&str>,
         cur_column: &mut u32,
         cur_breaks: &mut u32,
         implied_newline: &mut bool,
@@ -242,6 +505,7 @@ impl TtyState {
             term.set_attrs(lc.style, lc.fg, lc.bg)?;
             *cur_attr = (lc.style, lc.fg, lc.bg);
         }
+        term.set_link(link)?;
         let ch = lc.ch;
         let char_width = UnicodeWidthChar::width(ch).unwrap_or(0) as u32;
         if ch != '\n' {
@@ -255,7 +519,7 @@ impl TtyState {
         if (char_width > 0 && *cur_column >= term_width) || ch == '\n' {
             if *cur_column < term_width {
                 if cur_attr.0.contains(Style::INVERSE)
-                    || cur_attr.0.contains(Style::UNDERLINE)
+                    || cur_attr.0.intersects(Style::LINES)
                     || cur_attr.2.is_some()
                 {
                     term.print_spaces((term_width - *cur_column) as usize)?;
@@ -357,7 +621,9 @@ impl TtyState {
                         &mut output_cursor_top,
                         term_width,
                     );
-                    if a == b {
+                    let a_link = old_chars.as_ref().and_then(|x| x.cur_link());
+                    let b_link = new_chars.cur_link();
+                    if a == b && a_link == b_link {
                         self.sim_output_char(
                             term_width,
                             b,
@@ -387,6 +653,7 @@ impl TtyState {
                         term_width,
                         &mut cur_attr,
                         b,
+                        b_link,
                         &mut cur_column,
                         &mut cur_breaks,
                         &mut implied_newline,
@@ -396,6 +663,7 @@ impl TtyState {
                     endfill_redundant = a.endfills_same_as(&b);
                 }
                 (None, Some(b)) => {
+                    let b_link = new_chars.cur_link();
                     self.maybe_report(
                         b.index,
                         cur_column,
@@ -418,6 +686,7 @@ impl TtyState {
                         term_width,
                         &mut cur_attr,
                         b,
+                        b_link,
                         &mut cur_column,
                         &mut cur_breaks,
                         &mut implied_newline,
@@ -463,7 +732,7 @@ impl TtyState {
                 }
             }
         };
-        for b in new_chars {
+        while let Some(b) = new_chars.next() {
             debug_assert!(!ended_simultaneously);
             self.maybe_report(
                 b.index,
@@ -487,6 +756,7 @@ impl TtyState {
                 term_width,
                 &mut cur_attr,
                 b,
+                new_chars.cur_link(),
                 &mut cur_column,
                 &mut cur_breaks,
                 &mut implied_newline,
@@ -509,7 +779,7 @@ impl TtyState {
                     None => false,
                     Some(el) => {
                         el.style.contains(Style::INVERSE)
-                            || el.style.contains(Style::UNDERLINE)
+                            || el.style.intersects(Style::LINES)
                             || el.bg.is_some()
                     }
                 };
@@ -523,12 +793,14 @@ impl TtyState {
                     cur_breaks,
                 )?;
                 let last = new_line.elements.last().unwrap();
+                term.set_link(None)?;
                 term.set_attrs(last.style, last.fg, last.bg)?;
                 term.print_spaces((term_width - cur_column) as usize)?;
                 cur_column = term_width;
                 real_column = cur_column;
             }
         }
+        term.set_link(None)?;
         term.set_attrs(Style::empty(), None, None)?;
         if break_after && !implied_newline {
             if !endfill && cur_column != term_width {
@@ -558,6 +830,7 @@ impl TtyState {
                 term.carriage_return()?;
             }
         }
+        self.rollout_rows = cur_breaks + 1;
         self.remembered_output = Some(RememberedOutput {
             output_line: new_line.clone(),
             cursor_pos,
@@ -573,41 +846,95 @@ impl TtyState {
         request: Request,
     ) -> LifeOrDeath {
         match request {
-            Request::Output(line) | Request::OutputEcho(line) => {
-                self.rollin()?;
-                self.output_line(&line)?;
-                self.term.borrow_mut().reset_attrs()?;
+            Request::Output(line) => {
+                self.record(TranscriptKind::Output, &line)?;
+                self.output_recent_line(None, &line)?;
+            }
+            Request::OutputEcho(line) => {
+                self.record(TranscriptKind::Echo, &line)?;
+                self.output_recent_line(None, &line)?;
+            }
+            Request::OutputUpdatable(id, line) => {
+                self.record(TranscriptKind::Output, &line)?;
+                self.update_output(id, line)?;
             }
             #[cfg(feature = "capture-stderr")]
             Request::StderrLine(mut text) => {
                 if text.ends_with("\r") {
                     text.pop();
                 }
-                // TODO: custom decorators?
-                self.rollin()?;
-                self.output_line(&liso!(fg = red, bold, "E: ", -bold, text))?;
-                self.term.borrow_mut().reset_attrs()?;
+                self.record(TranscriptKind::Stderr, &Line::from_str(&text))?;
+                if self.stderr_responses {
+                    tx.send(Response::Stderr(text))?;
+                } else {
+                    let line = match self.stderr_decorator.as_ref() {
+                        Some(decorator) => decorator(text),
+                        None => Some(liso!(
+                            theme = "error",
+                            "E: ",
+                            theme = "stderr",
+                            text
+                        )),
+                    };
+                    if let Some(line) = line {
+                        self.output_recent_line(None, &line)?;
+                    }
+                }
             }
-            #[cfg(feature = "wrap")]
-            Request::OutputWrapped(mut line) => {
-                self.rollin()?;
-                line.wrap_to_width(self.term.borrow_mut().get_width() as usize);
-                self.output_line(&line)?;
-                self.term.borrow_mut().reset_attrs()?;
+            #[cfg(feature = "capture-stderr")]
+            Request::SetStderrDecorator(decorator) => {
+                self.stderr_decorator = decorator
             }
-            Request::SuspendAndRun(mut wat) => {
-                self.rollin()?;
-                self.remembered_output = None;
-                self.term.borrow_mut().suspend()?;
-                wat();
-                self.term.borrow_mut().unsuspend()?;
+            #[cfg(feature = "capture-stderr")]
+            Request::SetStderrResponses(enabled) => {
+                self.stderr_responses = enabled
+            }
+            Request::SetEditorSubmits(submits) => {
+                self.editor_submits = submits
             }
+            Request::SetKeymap(keymap) => self.keymap = keymap,
+            #[cfg(feature = "wrap")]
+            Request::OutputWrapped(mut line, options) => {
+                self.record(TranscriptKind::Output, &line)?;
+                line.wrap_to_width_with(
+                    self.term.borrow_mut().get_width() as usize,
+                    &options,
+                );
+                self.output_recent_line(None, &line)?;
+            }
+            Request::SuspendAndRun(wat) => self.suspend_and_run(wat)?,
             Request::Status(line) => {
                 if self.status != line {
                     self.rollout_needed = true;
                     self.status = line;
                 }
             }
+            Request::StatusSlot(key, line) => {
+                if self.status_area.set_line(key, line) {
+                    self.rollout_needed = true;
+                }
+            }
+            Request::StatusSlotLayout(key, order, align) => {
+                if self.status_area.set_layout(key, order, align) {
+                    self.rollout_needed = true;
+                }
+            }
+            Request::RemoveStatusSlot(key) => {
+                if self.status_area.remove(&key) {
+                    self.rollout_needed = true;
+                }
+            }
+            Request::Progress(key, shared) => {
+                self.status_area.set_progress(key, shared);
+                self.rollout_needed = true;
+                self.schedule_spinner_tick(ded_tx)?;
+            }
+            Request::ProgressChanged => self.rollout_needed = true,
+            Request::RemoveProgress(key, shared) => {
+                if self.status_area.remove_progress(&key, &shared) {
+                    self.rollout_needed = true;
+                }
+            }
             Request::Notice(line, duration) => {
                 self.show_notice(line, duration, ded_tx)?;
             }
@@ -626,6 +953,7 @@ impl TtyState {
                         self.input.clear();
                         #[cfg(feature = "history")]
                         {
+                            self.history_search = None;
                             self.cur_history_index = None;
                             self.orphaned_new_input = None;
                             self.history_original_line = None;
@@ -641,12 +969,26 @@ impl TtyState {
             Request::Key(code) => self.handle_key(tx, code, ded_tx)?,
             Request::Die => return Ok(()),
             Request::Heartbeat => {
+                let now = Instant::now();
                 if let Some((_, deadline)) = self.notice {
-                    if Instant::now() >= deadline {
+                    if now >= deadline {
                         self.rollout_needed = true;
                         self.notice = None;
                     }
                 }
+                if let Some(deadline) = self.spinner_tick {
+                    if now >= deadline {
+                        self.rollout_needed = true;
+                        self.spinner_tick = None;
+                        self.schedule_spinner_tick(ded_tx)?;
+                    }
+                }
+                if let Some(deadline) = self.chord_deadline {
+                    if now >= deadline {
+                        // (the rest of the chord never came)
+                        self.finish_chord(tx, ded_tx)?;
+                    }
+                }
             }
             Request::Custom(x) => tx.send(Response::Custom(x))?,
             #[cfg(feature = "history")]
@@ -679,6 +1021,39 @@ impl TtyState {
             }
             #[cfg(feature = "completion")]
             Request::SetCompletor(completor) => self.completor = completor,
+            Request::SetTranscript(transcript) => self.transcript = transcript,
+            Request::SetCastRecording(recording) => {
+                let starting = recording.is_some();
+                // start the recording with a clean slate, and then redraw
+                // everything into it
+                self.rollin()?;
+                self.rollout_needed = true;
+                let supported =
+                    self.term.borrow_mut().set_cast_recording(recording)?;
+                if starting && !supported {
+                    self.output_recent_line(
+                        None,
+                        &liso!(
+                            theme = "error",
+                            "Unable to record a cast: this terminal isn't \
+                             supported."
+                        ),
+                    )?;
+                }
+            }
+            Request::SetScrollbackLimit(limit) => {
+                self.scrollback_limit = limit;
+                while self.scrollback.len() > limit {
+                    self.scrollback.pop_front();
+                }
+                *self.scrollback_rows.get_mut() = None;
+            }
+            Request::SetTheme(theme) => {
+                if self.theme != theme {
+                    self.theme = theme;
+                    self.rollout_needed = true;
+                }
+            }
         }
         Ok(())
     }
@@ -811,6 +1186,7 @@ impl TtyState {
         self.rollin()?;
         self.rollout_needed = true;
         self.notice = None;
+        self.recent_output.clear();
         self.term.borrow_mut().clear_all_and_reset()?;
         Ok(())
     }
@@ -858,6 +1234,30 @@ impl TtyState {
         }
         Ok(())
     }
+    fn handle_transpose(&mut self) -> LifeOrDeath {
+        self.dismiss_notice()?;
+        // (at the end of the input, swap the last two characters)
+        let mut cursor = self.input_cursor;
+        if cursor == self.input.len() {
+            cursor -= self.input[..cursor]
+                .chars()
+                .next_back()
+                .map_or(0, char::len_utf8);
+        }
+        let (Some(before), Some(after)) = (
+            self.input[..cursor].chars().next_back(),
+            self.input[cursor..].chars().next(),
+        ) else {
+            return self.term.borrow_mut().bell();
+        };
+        let start = cursor - before.len_utf8();
+        let end = cursor + after.len_utf8();
+        self.input
+            .replace_range(start..end, &format!("{}{}", after, before));
+        self.input_cursor = end;
+        self.rollout_needed = true;
+        Ok(())
+    }
     fn handle_delete_fore(&mut self) -> LifeOrDeath {
         self.dismiss_notice()?;
         if self.input_cursor < self.input.len() {
@@ -931,6 +1331,7 @@ impl TtyState {
                 )?;
             }
         }
+        self.record(TranscriptKind::Input, &Line::from_str(&input))?;
         tx.send(Response::Input(input))?;
         Ok(())
     }
@@ -959,6 +1360,9 @@ impl TtyState {
         input: &str,
         ded_tx: &mut std_mpsc::SyncSender<Instant>,
     ) -> LifeOrDeath {
+        if self.review.is_some() {
+            return self.leave_review();
+        }
         if !self.input_allowed {
             return Ok(());
         }
@@ -973,115 +1377,314 @@ impl TtyState {
         ch: char,
         ded_tx: &mut std_mpsc::SyncSender<Instant>,
     ) -> LifeOrDeath {
-        if !self.input_allowed {
+        self.handle_key(tx, Key::Char(ch), ded_tx)
+    }
+    fn handle_key(
+        &mut self,
+        tx: &mut tokio_mpsc::UnboundedSender<Response>,
+        key: Key,
+        ded_tx: &mut std_mpsc::SyncSender<Instant>,
+    ) -> LifeOrDeath {
+        #[cfg(feature = "history")]
+        if self.history_search.is_some()
+            && self.review.is_none()
+            && self.input_allowed
+            && self.handle_search_key(key)?
+        {
+            return Ok(());
+        }
+        self.pending_keys.push(key);
+        match self.keymap.lookup(&self.pending_keys) {
+            Lookup::Prefix => {
+                // wait a moment to see if the rest of a chord follows
+                let deadline = Instant::now() + CHORD_DELAY;
+                self.chord_deadline = Some(deadline);
+                ded_tx.send(deadline)?;
+                Ok(())
+            }
+            Lookup::Bound => self.finish_chord(tx, ded_tx),
+            Lookup::Unbound if self.pending_keys.len() == 1 => {
+                self.finish_chord(tx, ded_tx)
+            }
+            Lookup::Unbound => {
+                // The keys before this one didn't turn out to be the start
+                // of a chord after all. They do what they do on their own,
+                // and then this key starts over.
+                let key = self.pending_keys.pop().unwrap();
+                self.finish_chord(tx, ded_tx)?;
+                self.handle_key(tx, key, ded_tx)
+            }
+        }
+    }
+    /// Does whatever the keys pressed so far do, and starts waiting for a
+    /// fresh key (or chord).
+    fn finish_chord(
+        &mut self,
+        tx: &mut tokio_mpsc::UnboundedSender<Response>,
+        ded_tx: &mut std_mpsc::SyncSender<Instant>,
+    ) -> LifeOrDeath {
+        self.chord_deadline = None;
+        let keys = std::mem::take(&mut self.pending_keys);
+        match (self.keymap.get(&keys).cloned(), keys.as_slice()) {
+            (Some(action), _) => self.perform(tx, &action, ded_tx),
+            (None, &[key]) => self.handle_unbound_key(tx, key),
+            // (an unbound chord)
+            (None, _) => self.term.borrow_mut().bell(),
+        }
+    }
+    /// If we're reviewing the scrollback, stops, and returns false. Otherwise,
+    /// returns whether input is allowed. Key presses that return false do
+    /// nothing else.
+    fn accept_key(&mut self) -> Result<bool, DummyError> {
+        if self.review.is_some() {
+            self.leave_review()?;
+            return Ok(false);
+        }
+        Ok(self.input_allowed)
+    }
+    fn handle_unbound_key(
+        &mut self,
+        tx: &mut tokio_mpsc::UnboundedSender<Response>,
+        key: Key,
+    ) -> LifeOrDeath {
+        if !self.accept_key()? {
             return Ok(());
         }
         #[cfg(feature = "completion")]
-        if ch == '\t' {
-            self.consecutive_completion_presses =
-                self.consecutive_completion_presses.saturating_add(1);
-        } else {
+        {
             self.consecutive_completion_presses = 0;
         }
-        match ch {
-            // Control-A (go to beginning of line)
-            '\u{0001}' => self.handle_home()?,
-            // Control-B (backward one char)
-            '\u{0002}' => self.handle_left_arrow()?,
-            // Control-C
-            '\u{0003}' => tx.send(Response::Quit)?,
-            // Control-D
-            '\u{0004}' => self.handle_finish(tx)?,
-            // Control-E (go to end of line)
-            '\u{0005}' => self.handle_end()?,
-            // Control-F (forward one char)
-            '\u{0006}' => self.handle_right_arrow()?,
-            // Control-G (discard input)
-            '\u{0007}' => self.handle_discard(tx)?,
-            // Control-K (kill line after cursor)
-            '\u{000B}' => self.handle_kill_to_end()?,
-            // Control-L (clear screen)
-            '\u{000C}' => self.handle_clear()?,
-            // Control-N (history next)
-            #[cfg(feature = "history")]
-            '\u{000E}' => self.history_next()?,
-            // Control-P (history previous)
-            #[cfg(feature = "history")]
-            '\u{0010}' => self.history_prev()?,
-            // Control-T
-            '\u{0014}' => tx.send(Response::Info)?,
-            // Control-U (kill line before cursor)
-            '\u{0015}' => self.handle_kill_to_start()?,
-            // Control-W (erase word)
-            '\u{0017}' => self.handle_delete_word()?,
-            // Control-X
-            '\u{0018}' => tx.send(Response::Swap)?,
-            // Control-Y (yank)
-            '\u{0019}' => self.handle_yank()?,
-            #[cfg(unix)]
-            // Control-Z
-            '\u{001A}' => self.handle_suspend()?,
-            // Tab
-            '\t' => self.handle_completion()?,
-            // Escape
-            '\u{001B}' => {
-                tx.send(Response::Escape)?;
-            }
-            // Soft break (control-backslash)
-            '\u{001C}' => {
-                tx.send(Response::Break)?;
-            }
-            // Enter/return
-            '\n' | '\r' => self.handle_return(tx, ded_tx)?,
-            // Backspace
-            '\u{0008}' | '\u{007F}' => self.handle_delete_back()?,
+        match key {
             // Unknown control character
-            '\u{0000}'..='\u{001F}' | '\u{0080}'..='\u{009F}' => {
+            Key::Char(
+                ch @ ('\u{0000}'..='\u{001F}' | '\u{0080}'..='\u{009F}'),
+            ) => {
                 tx.send(Response::Unknown(ch as u8))?;
             }
+            // DEL is Backspace, which only means something if it's bound,
+            // so an unbound DEL does nothing rather than sending `Unknown`
+            Key::Char('\u{007F}') => (),
             // Printable(?) text(??)
-            _ => self.handle_char_input(ch)?,
+            Key::Char(ch) => self.handle_char_input(ch)?,
+            _ => (),
         }
         Ok(())
     }
-    fn handle_key(
+    fn perform(
         &mut self,
         tx: &mut tokio_mpsc::UnboundedSender<Response>,
-        code: KeyCode,
+        action: &Action,
         ded_tx: &mut std_mpsc::SyncSender<Instant>,
     ) -> LifeOrDeath {
-        if !self.input_allowed {
+        // (reviewing the scrollback works even when input isn't allowed)
+        match action {
+            Action::ScrollbackPageUp => return self.handle_page_up(),
+            Action::ScrollbackPageDown => return self.handle_page_down(),
+            _ => (),
+        }
+        if !self.accept_key()? {
             return Ok(());
         }
-        self.consecutive_completion_presses = 0;
-        match code {
-            // Printable(?) text(??)
-            KeyCode::Backspace => self.handle_delete_back()?,
-            KeyCode::Delete => self.handle_delete_fore()?,
+        #[cfg(feature = "completion")]
+        if let Action::Complete = action {
+            self.consecutive_completion_presses =
+                self.consecutive_completion_presses.saturating_add(1);
+        } else {
+            self.consecutive_completion_presses = 0;
+        }
+        match action {
+            Action::BeginningOfLine => self.handle_home()?,
+            Action::EndOfLine => self.handle_end()?,
+            Action::BackwardChar => self.handle_left_arrow()?,
+            Action::ForwardChar => self.handle_right_arrow()?,
+            Action::DeleteBackward => self.handle_delete_back()?,
+            Action::DeleteForward => self.handle_delete_fore()?,
+            Action::DeleteWord => self.handle_delete_word()?,
+            Action::KillToEnd => self.handle_kill_to_end()?,
+            Action::KillToStart => self.handle_kill_to_start()?,
+            Action::Yank => self.handle_yank()?,
+            Action::TransposeChars => self.handle_transpose()?,
+            Action::Submit => self.handle_return(tx, ded_tx)?,
+            Action::Discard => self.handle_discard(tx)?,
+            Action::ClearOrFinish => self.handle_finish(tx)?,
+            Action::ClearScreen => self.handle_clear()?,
             #[cfg(feature = "history")]
-            KeyCode::Up => self.history_prev()?,
+            Action::HistoryPrevious => self.history_prev()?,
             #[cfg(feature = "history")]
-            KeyCode::Down => self.history_next()?,
-            KeyCode::Left => self.handle_left_arrow()?,
-            KeyCode::Right => self.handle_right_arrow()?,
-            KeyCode::Home => self.handle_home()?,
-            KeyCode::End => self.handle_end()?,
-            KeyCode::PageUp | KeyCode::PageDown => (), // TODO: handle?
+            Action::HistoryNext => self.history_next()?,
+            #[cfg(feature = "history")]
+            Action::HistorySearch => self.start_history_search()?,
+            #[cfg(not(feature = "history"))]
+            Action::HistoryPrevious
+            | Action::HistoryNext
+            | Action::HistorySearch => self.term.borrow_mut().bell()?,
+            Action::Complete => self.handle_completion()?,
+            #[cfg(unix)]
+            Action::Suspend => self.handle_suspend()?,
+            #[cfg(not(unix))]
+            Action::Suspend => self.term.borrow_mut().bell()?,
+            Action::EditInEditor => self.handle_edit_in_editor(tx, ded_tx)?,
+            // (handled above)
+            Action::ScrollbackPageUp | Action::ScrollbackPageDown => (),
+            Action::Respond(f) => tx.send(f())?,
+        }
+        Ok(())
+    }
+    /// If the cast recording stopped because of an error, tells the user
+    /// about it.
+    fn check_cast_error(&mut self) -> LifeOrDeath {
+        let error = self.term.borrow_mut().take_cast_error();
+        if let Some(e) = error {
+            let e = format!("Unable to write cast: {}", e);
+            self.output_recent_line(None, &liso!(theme = "error", e))?;
+            self.rollout()?;
+        }
+        Ok(())
+    }
+    /// Records a line in the transcript, if there is one.
+    fn record(&mut self, kind: TranscriptKind, line: &Line) -> LifeOrDeath {
+        let line = line.themed(&self.theme);
+        if let Some(error) =
+            record_transcript(&mut self.transcript, kind, &line)
+        {
+            self.output_recent_line(None, &error)?;
         }
         Ok(())
     }
+    /// The size of the terminal, as `(width, page_rows)`, where `page_rows`
+    /// is how many rows of scrollback fit on one page of review.
+    fn review_geometry(&self) -> (usize, usize) {
+        let mut term = self.term.borrow_mut();
+        let width = term.get_width() as usize;
+        // (leave the last row for the indicator)
+        let page_rows = (term.get_height() as usize).saturating_sub(1).max(1);
+        (width, page_rows)
+    }
+    /// The scrollback, as it would be laid out on a terminal of the given
+    /// width.
+    fn scrollback_rows(&self, width: usize) -> Rc<Vec<Line>> {
+        let mut cache = self.scrollback_rows.borrow_mut();
+        match cache.as_ref() {
+            Some((cached_width, rows)) if *cached_width == width => {
+                rows.clone()
+            }
+            _ => {
+                let rows: Rc<Vec<Line>> = Rc::new(
+                    self.scrollback
+                        .iter()
+                        .flat_map(|(_, line)| line.split_rows(width))
+                        .collect(),
+                );
+                *cache = Some((width, rows.clone()));
+                rows
+            }
+        }
+    }
+    fn handle_page_up(&mut self) -> LifeOrDeath {
+        let (width, page_rows) = self.review_geometry();
+        let total_rows = self.scrollback_rows(width).len();
+        let old_offset = self.review.unwrap_or(0);
+        let new_offset =
+            (old_offset + page_rows).min(total_rows.saturating_sub(page_rows));
+        if new_offset <= old_offset {
+            // (already at the top, or everything fits on the screen anyway)
+            return self.term.borrow_mut().bell();
+        }
+        if self.review.is_none() {
+            self.rollin()?;
+            self.notice = None;
+            self.recent_output.clear();
+            self.term.borrow_mut().clear_all_and_reset()?;
+        }
+        self.review = Some(new_offset);
+        self.rollout_needed = true;
+        Ok(())
+    }
+    fn handle_page_down(&mut self) -> LifeOrDeath {
+        let (_, page_rows) = self.review_geometry();
+        match self.review {
+            None => self.term.borrow_mut().bell(),
+            Some(offset) if offset <= page_rows => self.leave_review(),
+            Some(offset) => {
+                self.review = Some(offset - page_rows);
+                self.rollout_needed = true;
+                Ok(())
+            }
+        }
+    }
+    /// Stop reviewing the scrollback, putting the most recent output back on
+    /// the screen.
+    fn leave_review(&mut self) -> LifeOrDeath {
+        self.review = None;
+        self.rollin()?;
+        self.term.borrow_mut().clear_all_and_reset()?;
+        let (width, page_rows) = self.review_geometry();
+        let rows = self.scrollback_rows(width);
+        for row in rows[rows.len().saturating_sub(page_rows)..].iter() {
+            self.output_line(row)?;
+        }
+        self.term.borrow_mut().reset_attrs()?;
+        self.rollout_needed = true;
+        Ok(())
+    }
+    /// One page of the scrollback, starting `offset` rows back from the end,
+    /// followed by an indicator of where we are in it.
+    fn review_page(&self, offset: usize) -> Line {
+        let (width, page_rows) = self.review_geometry();
+        let rows = self.scrollback_rows(width);
+        let end = rows.len().saturating_sub(offset);
+        let start = end.saturating_sub(page_rows);
+        let mut ret = Line::new();
+        for row in rows[start..end].iter() {
+            ret.append_line(row);
+            ret.reset_and_break();
+        }
+        // (keep the indicator on the bottom row)
+        for _ in end - start..page_rows {
+            ret.reset_and_break();
+        }
+        // TODO: make localizable
+        let mut indicator = liso!(
+            inverse,
+            format!(
+                "Lines {}-{} of {}. PgUp/PgDn to scroll, any other key to \
+                 return.",
+                start + 1,
+                end,
+                rows.len()
+            )
+        );
+        // (one column short, so the terminal doesn't scroll)
+        indicator.truncate_to_width(width.saturating_sub(1), "…");
+        ret.append_line(&indicator);
+        ret
+    }
     fn show_notice(
         &mut self,
         line: Line,
         duration: Duration,
         ded_tx: &mut std_mpsc::SyncSender<Instant>,
     ) -> LifeOrDeath {
+        self.record(TranscriptKind::Notice, &line)?;
         self.rollout_needed = true;
         let deadline = Instant::now() + duration;
         self.notice = Some((line, deadline));
         ded_tx.send(deadline)?;
         Ok(())
     }
+    /// If any spinners are being displayed, and we haven't already arranged
+    /// to redraw them, arrange for a heartbeat when the next frame is due.
+    fn schedule_spinner_tick(
+        &mut self,
+        ded_tx: &mut std_mpsc::SyncSender<Instant>,
+    ) -> LifeOrDeath {
+        if self.spinner_tick.is_none() && self.status_area.has_spinners() {
+            let deadline = Instant::now() + SPINNER_INTERVAL;
+            self.spinner_tick = Some(deadline);
+            ded_tx.send(deadline)?;
+        }
+        Ok(())
+    }
     #[cfg(feature = "history")]
     fn history_prev(&mut self) -> LifeOrDeath {
         let history = self.history.read().unwrap();
@@ -1113,6 +1716,101 @@ impl TtyState {
         Ok(())
     }
     #[cfg(feature = "history")]
+    fn start_history_search(&mut self) -> LifeOrDeath {
+        self.dismiss_notice()?;
+        self.rollout_needed = true;
+        self.history_search = Some(HistorySearch {
+            query: String::new(),
+            found: None,
+            failing: false,
+            original_input: self.input.clone(),
+            original_cursor: self.input_cursor,
+        });
+        Ok(())
+    }
+    /// Finds the most recent line of history before `before` (or anywhere,
+    /// if `None`) that contains what we're searching for, and shows it.
+    #[cfg(feature = "history")]
+    fn search_history(&mut self, before: Option<usize>) -> LifeOrDeath {
+        self.rollout_needed = true;
+        let search = self.history_search.as_mut().unwrap();
+        let history = self.history.read().unwrap();
+        let lines = history.get_lines();
+        let before = before.unwrap_or(lines.len()).min(lines.len());
+        match lines[..before]
+            .iter()
+            .rposition(|x| x.contains(&search.query))
+        {
+            Some(i) => {
+                search.found = Some(i);
+                search.failing = false;
+                self.input = lines[i].clone();
+                self.input_cursor = self.input.find(&search.query).unwrap();
+            }
+            None => {
+                search.failing = true;
+                self.term.borrow_mut().bell()?;
+            }
+        }
+        Ok(())
+    }
+    /// Handles a key pressed while searching through history. Returns false
+    /// if the key ended the search, and should now do what it usually does.
+    #[cfg(feature = "history")]
+    fn handle_search_key(&mut self, key: Key) -> Result<bool, DummyError> {
+        self.dismiss_notice()?;
+        let action = self.keymap.get(&[key]);
+        let search_again = matches!(action, Some(Action::HistorySearch));
+        let shorten = matches!(action, Some(Action::DeleteBackward));
+        let give_up = matches!(action, Some(Action::Discard))
+            || key == Key::Char('\u{001B}');
+        let search = self.history_search.as_mut().unwrap();
+        match key {
+            Key::Char(ch) if !ch.is_control() => {
+                search.query.push(ch);
+                // (the line we're on might still match)
+                let before = search.found.map(|x| x + 1);
+                self.search_history(before)?;
+            }
+            _ if search_again => {
+                let before = search.found;
+                self.search_history(before)?;
+            }
+            _ if shorten => {
+                search.query.pop();
+                if search.query.is_empty() {
+                    search.found = None;
+                    search.failing = false;
+                    self.input = search.original_input.clone();
+                    self.input_cursor = search.original_cursor;
+                    self.rollout_needed = true;
+                } else {
+                    self.search_history(None)?;
+                }
+            }
+            _ if give_up => {
+                let search = self.history_search.take().unwrap();
+                self.input = search.original_input;
+                self.input_cursor = search.original_cursor;
+                self.rollout_needed = true;
+            }
+            _ => {
+                let search = self.history_search.take().unwrap();
+                self.rollout_needed = true;
+                if let Some(i) = search.found {
+                    // (the same as if we'd gotten here with history_prev)
+                    if self.orphaned_new_input.is_none() {
+                        self.orphaned_new_input = Some(search.original_input);
+                    }
+                    self.cur_history_index = Some(i);
+                    self.history_original_line = Some(self.input.clone());
+                }
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+    #[cfg(feature = "history")]
     fn history_next(&mut self) -> LifeOrDeath {
         let history = self.history.read().unwrap();
         match self.cur_history_index {
@@ -1145,10 +1843,71 @@ impl TtyState {
             .map(|i| history.get_lines()[i].clone());
         Ok(())
     }
+    /// Erases the prompt and status, and hands the terminal (and, if
+    /// possible, stdin) over to the given function until it returns.
+    fn suspend_and_run(&mut self, f: impl FnOnce()) -> LifeOrDeath {
+        self.review = None;
+        self.rollin()?;
+        self.remembered_output = None;
+        self.recent_output.clear();
+        self.term.borrow_mut().suspend()?;
+        // (so that a subprocess can have the user's input)
+        let paused = self.term.borrow_mut().pause_input();
+        f();
+        self.term.borrow_mut().unsuspend()?;
+        if paused {
+            self.term.borrow_mut().resume_input();
+        }
+        Ok(())
+    }
+    /// Opens the input in an external editor, and then either replaces the
+    /// input with the result or sends it, depending on `editor_submits`.
+    fn handle_edit_in_editor(
+        &mut self,
+        tx: &mut tokio_mpsc::UnboundedSender<Response>,
+        ded_tx: &mut std_mpsc::SyncSender<Instant>,
+    ) -> LifeOrDeath {
+        self.dismiss_notice()?;
+        let mut result = None;
+        let input = self.input.clone();
+        self.suspend_and_run(|| {
+            result = Some(editor::edit_in_editor(&input));
+        })?;
+        self.rollout_needed = true;
+        let text = match result.unwrap() {
+            Ok(text) => text,
+            Err(e) => {
+                // TODO: make localizable
+                let e = format!("Unable to edit input: {}", e);
+                return self.show_notice(
+                    liso!(inverse, e),
+                    Duration::from_secs(3),
+                    ded_tx,
+                );
+            }
+        };
+        // (control characters, such as tabs, would confuse the display)
+        let lines = text
+            .lines()
+            .map(|x| x.replace(|ch: char| ch.is_control(), " "));
+        if self.editor_submits {
+            self.input.clear();
+            self.input_cursor = 0;
+            for line in lines {
+                self.input = line;
+                self.handle_return(tx, ded_tx)?;
+            }
+        } else {
+            self.input = lines.collect::<Vec<_>>().join(" ");
+            self.input_cursor = self.input.len();
+        }
+        Ok(())
+    }
     #[cfg(unix)]
     fn handle_suspend(&mut self) -> LifeOrDeath {
         self.rollout()?;
         self.remembered_output = None;
+        self.recent_output.clear();
         let mut term = self.term.borrow_mut();
         term.set_attrs(Style::PLAIN, None, None)?;
         term.suspend()?;
@@ -1228,6 +1987,12 @@ impl TtyState {
             return Ok(());
         }
         self.rollout_needed = false;
+        if let Some(offset) = self.review {
+            let page = self.review_page(offset);
+            self.term.borrow_mut().hide_cursor()?;
+            self.output_line_changes(&page, None, false, true)?;
+            return self.term.borrow_mut().flush();
+        }
         let mut new_output = match self.status.as_ref() {
             None => Line::new(),
             Some(status) => {
@@ -1236,17 +2001,26 @@ impl TtyState {
                 line
             }
         };
+        let term_width = self.term.borrow_mut().get_width() as usize;
+        self.status_area.render_into(&mut new_output, term_width);
         let cursor_pos;
         if let Some((line, _)) = self.notice.as_ref() {
             new_output.append_line(line);
             cursor_pos = None;
         } else {
-            if let Some(line) = self.prompt.as_ref() {
+            #[cfg(feature = "history")]
+            let search_prompt =
+                self.history_search.as_ref().map(HistorySearch::prompt);
+            #[cfg(not(feature = "history"))]
+            let search_prompt: Option<Line> = None;
+            if let Some(line) = search_prompt.as_ref().or(self.prompt.as_ref())
+            {
                 new_output.append_line(line);
             }
             cursor_pos = Some(self.input_cursor + new_output.len());
             new_output.add_text(&self.input);
         }
+        let new_output = new_output.themed(&self.theme).into_owned();
         self.term.borrow_mut().hide_cursor()?;
         self.output_line_changes(&new_output, cursor_pos, false, true)?;
         let mut term = self.term.borrow_mut();
@@ -1284,18 +2058,21 @@ fn tty_worker(
                     };
                 } else {
                     let now = Instant::now();
-                    if !deadlines.is_empty() && now >= deadlines[0] {
-                        deadlines.remove(0);
-                        match req_tx_clone.send(Request::Heartbeat) {
-                            Ok(_) => break,
-                            Err(_) => return,
+                    if now >= deadlines[0] {
+                        deadlines.retain(|x| *x > now);
+                        if req_tx_clone.send(Request::Heartbeat).is_err() {
+                            return;
                         }
                     }
                     if !deadlines.is_empty() {
                         use std::sync::mpsc::RecvTimeoutError;
                         let interval = deadlines[0] - now;
                         match ded_rx.recv_timeout(interval) {
-                            Ok(x) => deadlines.push(x),
+                            // (kept sorted, soonest first)
+                            Ok(x) => deadlines.insert(
+                                deadlines.partition_point(|y| *y <= x),
+                                x,
+                            ),
                             Err(RecvTimeoutError::Timeout) => (),
                             Err(RecvTimeoutError::Disconnected) => return,
                         }
@@ -1307,9 +2084,19 @@ fn tty_worker(
     let term = new_term(&req_tx)?;
     let mut state = TtyState {
         status: None,
+        status_area: StatusArea::default(),
         prompt: None,
         notice: None,
+        spinner_tick: None,
         remembered_output: None,
+        rollout_rows: 0,
+        recent_output: VecDeque::new(),
+        recent_output_width: 0,
+        scrollback: VecDeque::new(),
+        scrollback_limit: DEFAULT_SCROLLBACK_LIMIT,
+        scrollback_rows: RefCell::new(None),
+        review: None,
+        transcript: None,
         input_allowed: true,
         input: String::new(),
         input_cursor: 0,
@@ -1324,8 +2111,19 @@ fn tty_worker(
         orphaned_new_input: None,
         #[cfg(feature = "history")]
         history_original_line: None,
+        theme: Theme::default(),
         #[cfg(feature = "completion")]
         completor: None,
+        #[cfg(feature = "capture-stderr")]
+        stderr_decorator: None,
+        #[cfg(feature = "capture-stderr")]
+        stderr_responses: false,
+        keymap: Keymap::default(),
+        pending_keys: Vec::new(),
+        chord_deadline: None,
+        #[cfg(feature = "history")]
+        history_search: None,
+        editor_submits: false,
         #[cfg(feature = "completion")]
         consecutive_completion_presses: 0,
         #[cfg(feature = "completion")]
@@ -1346,9 +2144,12 @@ fn tty_worker(
             match rx.try_recv() {
                 Ok(Request::Die) => {
                     dying = true;
-                    if cfg!(not(feature = "capture-stderr")) {
-                        // if we're not capturing stderr, there's no reason not
-                        // to break immediately
+                    if cfg!(not(any(
+                        feature = "capture-stderr",
+                        feature = "capture-stdout"
+                    ))) {
+                        // if we're not capturing anything, there's no reason
+                        // not to break immediately
                         break;
                     }
                 }
@@ -1358,6 +2159,7 @@ fn tty_worker(
             }
         }
         state.rollout()?;
+        state.check_cast_error()?;
     }
     state.rollin()?;
     state.cleanup()?;
@@ -1382,8 +2184,8 @@ pub(crate) fn worker(
     {
         pipe_worker(req_tx, rx, tx)
     } else {
-        #[cfg(feature = "capture-stderr")]
-        stderr_capture::attempt_stderr_capture(Output { tx: req_tx.clone() });
+        #[cfg(any(feature = "capture-stderr", feature = "capture-stdout"))]
+        capture::attempt_captures(&Output { tx: req_tx.clone() });
         #[cfg(feature = "history")]
         return tty_worker(req_tx, rx, tx, history);
         #[cfg(not(feature = "history"))]
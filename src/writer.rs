@@ -0,0 +1,131 @@
+//! An adapter that lets code that writes to an `io::Write` print through
+//! Liso.
+
+use super::*;
+
+use std::io;
+
+/// Implements [`std::io::Write`], printing everything written to it as
+/// output, one line at a time. Create one with
+/// [`Output::writer`](struct.Output.html#method.writer).
+///
+/// Anything that can write to an `io::Write` (a serializer, a report
+/// generator, code full of `writeln!`) can write to this instead of to
+/// stdout, without scrambling the prompt. Text is held until a newline is
+/// written, and then the completed line is printed with `println`. Whatever
+/// is left over when the writer is dropped is printed as a line of its own.
+/// (`flush` doesn't print a line that hasn't been finished, since that would
+/// split it in two.)
+///
+/// Invalid UTF-8 is replaced with U+FFFD. Line endings can be `\n` or
+/// `\r\n`.
+///
+/// ```rust,no_run
+/// # use std::io::Write;
+/// # let io = liso::InputOutput::new();
+/// let mut writer = io.writer();
+/// writeln!(writer, "{} + {} = {}", 2, 2, 4).unwrap();
+/// ```
+pub struct OutputWriter {
+    output: OutputOnly,
+    ansi: bool,
+    buf: Vec<u8>,
+    /// When parsing ANSI, the style, colors, and link in effect at the end of
+    /// the last line, to carry over to the next one.
+    carry: Line,
+}
+
+impl OutputWriter {
+    pub(crate) fn new(output: OutputOnly) -> OutputWriter {
+        OutputWriter {
+            output,
+            ansi: false,
+            buf: Vec::new(),
+            carry: Line::new(),
+        }
+    }
+    /// Sets whether the text written contains ANSI escape sequences that
+    /// should be turned into styles, the same as
+    /// [`Line::add_ansi_text`](struct.Line.html#method.add_ansi_text). If
+    /// so, styles carry over from one line to the next, the same as they
+    /// would on a terminal. The default is not to parse them.
+    pub fn ansi(mut self, ansi: bool) -> OutputWriter {
+        self.ansi = ansi;
+        self
+    }
+    /// Prints the given bytes as one line.
+    fn print_line(&mut self, bytes: &[u8]) {
+        let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+        let text = String::from_utf8_lossy(bytes);
+        if !self.ansi {
+            self.output.println(text);
+            return;
+        }
+        let mut line = Line::new();
+        line.set_style(self.carry.get_style());
+        let (fg, bg) = self.carry.get_colors();
+        line.set_colors(fg, bg);
+        line.set_link(self.carry.get_link());
+        line.add_ansi_text(text);
+        self.carry = Line::new();
+        self.carry.set_style(line.get_style());
+        let (fg, bg) = line.get_colors();
+        self.carry.set_colors(fg, bg);
+        self.carry.set_link(line.get_link());
+        self.output.println(line);
+    }
+}
+
+impl io::Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(pos) = rest.iter().position(|x| *x == b'\n') {
+            if self.buf.is_empty() {
+                self.print_line(&rest[..pos]);
+            } else {
+                let mut line = std::mem::take(&mut self.buf);
+                line.extend_from_slice(&rest[..pos]);
+                self.print_line(&line);
+            }
+            rest = &rest[pos + 1..];
+        }
+        self.buf.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for OutputWriter {
+    fn drop(&mut self) {
+        if !self.buf.is_empty() {
+            let line = std::mem::take(&mut self.buf);
+            self.print_line(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    #[test]
+    fn output_writer() {
+        let (output, rx) = Output::for_test();
+        let mut writer = output.writer().ansi(true);
+        write!(writer, "one\r\ntw").unwrap();
+        write!(writer, "o\x1B[1m\nthree\x1B[0m\nfo").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        assert_eq!(
+            sent_lines(&rx),
+            [
+                liso!("one"),
+                liso!("two", bold),
+                liso!(bold, "three", plain),
+                liso!("fo")
+            ]
+        );
+    }
+}